@@ -0,0 +1,152 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::game::GameEvent;
+
+const LOCK_FLASH: Duration = Duration::from_millis(150);
+const CLEAR_FLASH: Duration = Duration::from_millis(120);
+const CLEAR_COLLAPSE: Duration = Duration::from_millis(180);
+const DROP_TRAIL: Duration = Duration::from_millis(150);
+const MILESTONE: Duration = Duration::from_millis(2000);
+const CELEBRATION: Duration = Duration::from_millis(4000);
+
+pub enum Effect {
+    LockFlash {
+        cells: Vec<(usize, usize)>,
+    },
+
+    RowClear {
+        rows: Vec<usize>,
+    },
+
+    DropTrail {
+        piece_index: usize,
+        orient: u8,
+        x: usize,
+        from_y: usize,
+        to_y: usize,
+    },
+
+    /// Banner about the garbage left.
+    Milestone {
+        left: usize,
+    },
+
+    /// Falls over the board when the garbage is gone.
+    Confetti,
+}
+
+pub struct Animation {
+    pub start: Instant,
+    pub effect: Effect,
+}
+
+impl Animation {
+    fn duration(&self) -> Duration {
+        match self.effect {
+            Effect::LockFlash { .. } => LOCK_FLASH,
+            Effect::RowClear { .. } => CLEAR_FLASH + CLEAR_COLLAPSE,
+            Effect::DropTrail { .. } => DROP_TRAIL,
+            Effect::Milestone { left: 0 } | Effect::Confetti => CELEBRATION,
+            Effect::Milestone { .. } => MILESTONE,
+        }
+    }
+
+    /// Fraction of the animation which has been played, between 0 and 1.
+    pub fn progress(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.start);
+        f64::min(elapsed.as_secs_f64() / self.duration().as_secs_f64(), 1.0)
+    }
+
+    /// How far cleared rows have collapsed, between 0 and 1.  Rows flash in
+    /// place before they start to collapse.
+    pub fn collapse(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed < CLEAR_FLASH {
+            0.0
+        } else {
+            let t = (elapsed - CLEAR_FLASH).as_secs_f64() / CLEAR_COLLAPSE.as_secs_f64();
+            ease_out(f64::min(t, 1.0))
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Animations {
+    pub list: Vec<Animation>,
+}
+
+impl Animations {
+    pub fn start(&mut self, event: GameEvent, now: Instant) {
+        let effect = match event {
+            GameEvent::Dropped {
+                piece_index,
+                orient,
+                x,
+                from_y,
+                to_y,
+            } => Effect::DropTrail {
+                piece_index,
+                orient,
+                x,
+                from_y,
+                to_y,
+            },
+
+            GameEvent::Cleared { rows } => Effect::RowClear { rows },
+            GameEvent::Locked { cells, .. } => Effect::LockFlash { cells },
+
+            // Whatever was animating is gone from the world.
+            GameEvent::Undone => {
+                self.list.clear();
+                return;
+            }
+
+            GameEvent::Milestone { left } => {
+                self.milestone(left, true, now);
+                return;
+            }
+        };
+
+        self.list.push(Animation { start: now, effect });
+    }
+
+    /// Announces a milestone, celebrating with confetti if motion is welcome
+    /// and the garbage is gone.
+    pub fn milestone(&mut self, left: usize, motion: bool, now: Instant) {
+        self.list.push(Animation {
+            start: now,
+            effect: Effect::Milestone { left },
+        });
+
+        if left == 0 && motion {
+            self.list.push(Animation {
+                start: now,
+                effect: Effect::Confetti,
+            });
+        }
+    }
+
+    pub fn prune(&mut self, now: Instant) {
+        self.list.retain(|anim| anim.progress(now) < 1.0);
+    }
+
+    /// How many cells a world row is still displaced upwards by collapsing
+    /// line clears below it.
+    pub fn collapse_shift(&self, y: usize, now: Instant) -> f64 {
+        let mut shift = 0.0;
+
+        for anim in self.list.iter() {
+            if let Effect::RowClear { rows } = &anim.effect {
+                let count = rows.iter().filter(|boundary| **boundary <= y).count();
+                shift += count as f64 * (1.0 - anim.collapse(now));
+            }
+        }
+
+        shift
+    }
+}
+
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
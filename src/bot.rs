@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+
+use crate::game::spawn_piece;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::play::Input;
+use crate::play::Play;
+use crate::search::placements;
+use crate::search::Placement;
+use crate::search::Spot;
+
+/// Rows of an endless tower the bot looks at, counting down from the top.
+/// The whole tower would be too much to copy for every candidate, and what
+/// lies deeper hardly matters.
+const VIEW_ROWS: usize = 24;
+
+/// How much each feature of a board counts towards its score.  Features
+/// which are bad to have get negative weights.
+#[derive(Clone, Copy)]
+pub struct Weights {
+    /// Sum of the column heights.
+    pub height: f64,
+    /// Lines cleared on the way to the board.
+    pub lines: f64,
+    /// Empty cells with a block somewhere above them.
+    pub holes: f64,
+    /// Sum of the height differences between neighbouring columns.
+    pub bumpiness: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            height: -0.51,
+            lines: 0.76,
+            holes: -0.36,
+            bumpiness: -0.18,
+        }
+    }
+}
+
+impl Weights {
+    /// Parses comma-separated name=value pairs, such as "holes=-1,lines=2".
+    /// Features which aren't named keep their default weights.
+    pub fn parse(text: &str) -> Option<Weights> {
+        let mut weights = Weights::default();
+
+        for pair in text.split(',') {
+            let (name, value) = pair.split_once('=')?;
+            let value: f64 = value.trim().parse().ok().filter(|v: &f64| v.is_finite())?;
+
+            match name.trim() {
+                "height" => weights.height = value,
+                "lines" => weights.lines = value,
+                "holes" => weights.holes = value,
+                "bumpiness" => weights.bumpiness = value,
+                _ => return None,
+            }
+        }
+
+        Some(weights)
+    }
+
+    /// Scores a board reached by clearing some lines.
+    pub fn evaluate(&self, game: &Game, lines: usize) -> f64 {
+        let heights: Vec<usize> = (0..game.width)
+            .map(|x| {
+                (0..game.world.len())
+                    .rev()
+                    .find(|y| !game.world[*y][x].is_empty())
+                    .map_or(0, |y| y + 1)
+            })
+            .collect();
+
+        let holes: usize = heights
+            .iter()
+            .enumerate()
+            .map(|(x, height)| {
+                (0..*height)
+                    .filter(|y| game.world[*y][x].is_empty())
+                    .count()
+            })
+            .sum();
+
+        let bumpiness: usize = heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+
+        self.height * heights.iter().sum::<usize>() as f64
+            + self.lines * lines as f64
+            + self.holes * holes as f64
+            + self.bumpiness * bumpiness as f64
+    }
+}
+
+/// Copies what the bot needs of a game, leaving out the history and the
+/// depths of an endless tower.  Returns the copy and the row it starts at.
+fn view(game: &Game) -> (Game, usize) {
+    let floor = view_floor(game);
+
+    let view = Game {
+        pieces: game.pieces.clone(),
+        width: game.width,
+        world: game.world[floor..].to_vec(),
+        next_gen: game.next_gen,
+        history: Vec::new(),
+        layer: 0,
+        session: game.session,
+        time: game.time,
+        piece_index: game.piece_index,
+        next_piece_index: game.next_piece_index,
+        orient: game.orient,
+        y: game.y - floor,
+        x: game.x,
+        rotated: game.rotated,
+        well: game.well,
+        topped_out: game.topped_out,
+    };
+
+    (view, floor)
+}
+
+/// The lowest row of a game the bot looks at.
+pub fn view_floor(game: &Game) -> usize {
+    match game.well {
+        Some(_) => 0,
+        None => game.world.len().saturating_sub(VIEW_ROWS),
+    }
+}
+
+/// Every placement of the active piece within the rows the bot looks at.
+/// Spots and paths are in the game's rows, but the games of the placements
+/// are cut down to the view.
+pub fn visible_placements(game: &Game) -> Vec<Placement> {
+    let (view, floor) = view(game);
+    let raise = |spot: Spot| Spot {
+        y: spot.y + floor,
+        ..spot
+    };
+
+    placements(&view)
+        .into_iter()
+        .map(|placement| Placement {
+            spot: raise(placement.spot),
+            path: placement
+                .path
+                .into_iter()
+                .map(|(spot, input)| (raise(spot), input))
+                .collect(),
+            ..placement
+        })
+        .collect()
+}
+
+/// Decides where the pieces of a game go.  A player which can't go on
+/// returns an error, which ends the game.
+pub trait Player {
+    /// Inputs which take the active piece where it should go, ending with the
+    /// one which locks it, each with where the piece should be when it's
+    /// given.
+    fn plan(&mut self, play: &Play) -> Result<Vec<(Spot, Input)>, String>;
+
+    /// Hears of a piece which locked, with the game as it is afterwards.
+    fn locked(&mut self, _play: &Play, _event: &GameEvent) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// The game was started over.
+    fn restart(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Picks placements by scoring the boards they lead to.
+#[derive(Clone, Copy)]
+pub struct Bot {
+    pub weights: Weights,
+    /// Also try every placement of the next piece, and score each placement
+    /// by the best board it allows.
+    pub lookahead: bool,
+}
+
+impl Bot {
+    /// The placement of the active piece the bot likes best.  Its spot and
+    /// path are in the game's rows, but its game is cut down to what the bot
+    /// looks at.
+    pub fn choose(&self, game: &Game) -> Option<Placement> {
+        visible_placements(game)
+            .into_iter()
+            .map(|placement| (self.score(&placement), placement))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, placement)| placement)
+    }
+
+    fn score(&self, placement: &Placement) -> f64 {
+        if placement.game.topped_out {
+            return f64::NEG_INFINITY;
+        }
+
+        let here = self.weights.evaluate(&placement.game, placement.lines);
+        if !self.lookahead {
+            return here;
+        }
+
+        let mut next = placement.game.clone();
+        spawn_piece(&mut next, placement.game.next_piece_index);
+
+        placements(&next)
+            .iter()
+            .filter(|after| !after.game.topped_out)
+            .map(|after| {
+                self.weights
+                    .evaluate(&after.game, placement.lines + after.lines)
+            })
+            .max_by(f64::total_cmp)
+            .unwrap_or(f64::NEG_INFINITY)
+    }
+}
+
+impl Player for Bot {
+    fn plan(&mut self, play: &Play) -> Result<Vec<(Spot, Input)>, String> {
+        Ok(self
+            .choose(&play.game)
+            .map(|placement| placement.path)
+            .unwrap_or_default())
+    }
+}
+
+/// The bot's advice for the player's active piece, worked out once per
+/// piece.
+pub struct Hint {
+    bot: Bot,
+    /// Pieces placed and undos used when the advice was worked out.
+    key: Option<(u32, u32)>,
+    spot: Option<Spot>,
+}
+
+impl Hint {
+    pub fn new(bot: Bot) -> Hint {
+        Hint {
+            bot,
+            key: None,
+            spot: None,
+        }
+    }
+
+    /// Where the bot would put the active piece.
+    pub fn spot(&mut self, play: &Play) -> Option<Spot> {
+        let key = (play.stats.pieces, play.stats.undos);
+
+        if self.key != Some(key) {
+            self.spot = self.bot.choose(&play.game).map(|placement| placement.spot);
+            self.key = Some(key);
+        }
+
+        self.spot
+    }
+
+    /// Forgets the advice, for a game which has been started over.
+    pub fn forget(&mut self) {
+        self.key = None;
+    }
+}
+
+/// Plays a game for a player other than the human, one input every few
+/// ticks.  Soft drops only take the piece down a row per tick, and a slower
+/// pace can be watched.
+pub struct Autoplay {
+    player: Box<dyn Player>,
+    /// Ticks from one input to the next, at least 1.
+    delay: u32,
+    plan: VecDeque<(Spot, Input)>,
+    /// Pieces placed when the plan was made.
+    pieces: u32,
+    wait: u32,
+}
+
+impl Autoplay {
+    pub fn new(player: Box<dyn Player>, delay: u32) -> Autoplay {
+        Autoplay {
+            player,
+            delay: u32::max(delay, 1),
+            plan: VecDeque::new(),
+            pieces: 0,
+            wait: 0,
+        }
+    }
+
+    /// Inputs for the next tick.
+    pub fn inputs(&mut self, play: &Play) -> Result<Vec<Input>, String> {
+        if play.over() {
+            self.plan.clear();
+            return Ok(Vec::new());
+        }
+
+        if self.wait > 0 {
+            self.wait -= 1;
+            return Ok(Vec::new());
+        }
+
+        // Gravity can move the piece off the plan, or lock it early.
+        let on_plan = self.pieces == play.stats.pieces
+            && self
+                .plan
+                .front()
+                .is_some_and(|(spot, _)| *spot == Spot::of(&play.game));
+
+        if !on_plan {
+            self.plan = self.player.plan(play)?.into();
+            self.pieces = play.stats.pieces;
+        }
+
+        self.wait = self.delay - 1;
+        Ok(self
+            .plan
+            .pop_front()
+            .map(|(_, input)| input)
+            .into_iter()
+            .collect())
+    }
+
+    /// Passes on what happened during a tick.
+    pub fn observe(&mut self, play: &Play, events: &[GameEvent]) -> Result<(), String> {
+        for event in events.iter() {
+            if let GameEvent::Locked { .. } = event {
+                self.player.locked(play, event)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the game, which has been started over.
+    pub fn restart(&mut self) -> Result<(), String> {
+        self.plan.clear();
+        self.pieces = 0;
+        self.wait = 0;
+        self.player.restart()
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// How quickly the camera closes in on its target, per second.
+const CAMERA_RATE: f64 = 8.0;
+
+/// Vertical view position, measured in rows from the bottom of the world.
+#[derive(Clone)]
+pub struct Camera {
+    pub level: f64,
+    /// Scale of the world relative to the normal cell size.
+    pub zoom: f64,
+    /// Set while the player is looking around freely.
+    pub explore: bool,
+}
+
+impl Camera {
+    pub fn new(level: f64) -> Camera {
+        Camera {
+            level,
+            zoom: 1.0,
+            explore: false,
+        }
+    }
+
+    /// Eases towards the target level and zoom.  The result doesn't depend on
+    /// how the elapsed time is split between calls.
+    pub fn update(&mut self, level: f64, zoom: f64, elapsed: Duration) {
+        let t = 1.0 - (-CAMERA_RATE * elapsed.as_secs_f64()).exp();
+
+        self.level += (level - self.level) * t;
+        if (level - self.level).abs() < 0.001 {
+            self.level = level;
+        }
+
+        self.zoom += (zoom - self.zoom) * t;
+        if (zoom - self.zoom).abs() < 0.001 {
+            self.zoom = zoom;
+        }
+    }
+}
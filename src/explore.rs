@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::camera::Camera;
+use crate::font::render_text;
+use crate::font::text_height;
+use crate::game::Game;
+use crate::game::START_HEIGHT;
+use crate::game::UNKNOWN_PIECE;
+use crate::layout::Layout;
+use crate::legend::format_time;
+use crate::render::cell_at;
+use crate::render::cell_size;
+use crate::render::level_y;
+use crate::render::minimap_row_at;
+use crate::render::visible_levels;
+use crate::render::Canvas;
+
+const MIN_ZOOM: f64 = 1.0 / 32.0;
+const MAX_ZOOM: f64 = 2.0;
+const WHEEL_ROWS: f64 = 3.0;
+
+/// Free look around the tower while the game is paused.
+pub struct Explore {
+    pub camera: Camera,
+    level: f64,
+    zoom: f64,
+    mouse: Option<(i32, i32)>,
+    ctrl: bool,
+}
+
+impl Explore {
+    pub fn new(camera: &Camera) -> Explore {
+        Explore {
+            camera: Camera {
+                explore: true,
+                ..camera.clone()
+            },
+            level: camera.level,
+            zoom: camera.zoom,
+            mouse: None,
+            ctrl: false,
+        }
+    }
+
+    /// True when the camera has reached its target.
+    pub fn settled(&self) -> bool {
+        self.camera.level == self.level && self.camera.zoom == self.zoom
+    }
+
+    pub fn update(&mut self, elapsed: Duration) {
+        self.camera.update(self.level, self.zoom, elapsed);
+    }
+
+    fn scroll(&mut self, game: &Game, rows: f64) {
+        let top = (game.world.len() + START_HEIGHT) as f64;
+        self.level = (self.level + rows).clamp(0.0, top);
+    }
+
+    fn zoom_by(&mut self, factor: f64) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    fn page_rows(&self, layout: &Layout) -> f64 {
+        let (top, bottom) = visible_levels(
+            layout,
+            &Camera {
+                zoom: self.zoom,
+                ..self.camera.clone()
+            },
+        );
+
+        f64::max(1.0, (top - bottom) * 0.75)
+    }
+
+    pub fn handle_event(&mut self, layout: &Layout, game: &Game, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Up | Keycode::W => self.scroll(game, 1.0),
+                Keycode::Down | Keycode::S => self.scroll(game, -1.0),
+                Keycode::PageUp => self.scroll(game, self.page_rows(layout)),
+                Keycode::PageDown => self.scroll(game, -self.page_rows(layout)),
+                Keycode::Home => self.level = game.world.len() as f64,
+                Keycode::End => self.level = 0.0,
+                Keycode::Plus | Keycode::Equals | Keycode::KpPlus => self.zoom_by(2.0),
+                Keycode::Minus | Keycode::KpMinus => self.zoom_by(0.5),
+                Keycode::LCtrl | Keycode::RCtrl => self.ctrl = true,
+                _ => {}
+            },
+
+            Event::KeyUp {
+                keycode: Some(Keycode::LCtrl | Keycode::RCtrl),
+                ..
+            } => {
+                self.ctrl = false;
+            }
+
+            Event::MouseWheel { y, .. } => {
+                if self.ctrl {
+                    self.zoom_by(2f64.powi(y));
+                } else {
+                    self.scroll(game, y as f64 * WHEEL_ROWS / self.zoom);
+                }
+            }
+
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse = Some(layout.to_pixels(x, y));
+            }
+
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let (x, y) = layout.to_pixels(x, y);
+
+                if let Some(row) = minimap_row_at(layout, game, &self.camera, x, y) {
+                    self.level = (row + 1) as f64;
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    pub fn render(&self, canvas: &mut Canvas, layout: &Layout, game: &Game) {
+        let scale = layout.text_scale();
+        let margin = layout.text_margin();
+        let line_height = text_height(scale) as i32 + margin;
+
+        render_text(
+            canvas,
+            layout.left + margin,
+            margin,
+            scale,
+            Color::RGB(255, 255, 255),
+            "EXPLORE - P RESUME, E BACK",
+        );
+
+        let hovered = self
+            .mouse
+            .and_then(|(x, y)| cell_at(layout, game, &self.camera, x, y));
+
+        if let Some((x, y)) = hovered {
+            let cell = cell_size(layout, &self.camera);
+
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            canvas
+                .draw_rect(Rect::new(
+                    layout.left + x as i32 * cell,
+                    level_y(layout, &self.camera, (y + 1) as f64),
+                    cell as u32,
+                    cell as u32,
+                ))
+                .unwrap();
+
+            let depth = game.world.len() - y;
+            let cell = game.world[y][x];
+            let mut lines = Vec::new();
+
+            if cell.is_empty() {
+                lines.push(format!("DEPTH {} EMPTY", depth));
+            } else {
+                lines.push(format!(
+                    "DEPTH {} GEN {} AGE {}",
+                    depth,
+                    cell.gen,
+                    game.next_gen - 1 - cell.gen
+                ));
+
+                // Where the tower keeps a record of the placement.
+                let generation = game.history[cell.gen as usize];
+                let mut placed = Vec::new();
+
+                if generation.time != 0 {
+                    placed.push(format_time(generation.time));
+                }
+                if generation.session != 0 {
+                    placed.push(format!("SESSION {}", generation.session));
+                }
+                if let Some(name) = game.pieces.names.get(generation.piece as usize) {
+                    if generation.piece != UNKNOWN_PIECE {
+                        placed.push(format!("PIECE {}", name));
+                    }
+                }
+
+                if !placed.is_empty() {
+                    lines.push(placed.join(" "));
+                }
+            }
+
+            let box_height = lines.len() as i32 * line_height + margin;
+
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, 191));
+            canvas
+                .fill_rect(Rect::new(
+                    layout.left,
+                    layout.height - box_height,
+                    layout.board_width as u32,
+                    box_height as u32,
+                ))
+                .unwrap();
+
+            for (n, text) in lines.iter().enumerate() {
+                render_text(
+                    canvas,
+                    layout.left + margin,
+                    layout.height - box_height + margin + n as i32 * line_height,
+                    scale,
+                    Color::RGB(255, 255, 255),
+                    text,
+                );
+            }
+        }
+    }
+}
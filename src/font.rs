@@ -0,0 +1,110 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::render::Canvas;
+
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_HEIGHT: i32 = 5;
+const GLYPH_ADVANCE: i32 = GLYPH_WIDTH + 1;
+
+/// 3x5 pixel glyphs, one row per byte with the leftmost pixel in bit 2.
+static GLYPHS: [(char, [u8; 5]); 56] = [
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b011, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b010, 0b100, 0b100, 0b100, 0b010]),
+    (')', [0b010, 0b001, 0b001, 0b001, 0b010]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('|', [0b010, 0b010, 0b010, 0b010, 0b010]),
+];
+
+fn glyph(c: char) -> &'static [u8; 5] {
+    let c = c.to_ascii_uppercase();
+
+    match GLYPHS.iter().find(|(g, _)| *g == c) {
+        Some((_, bits)) => bits,
+        None => glyph('?'),
+    }
+}
+
+pub fn text_height(scale: u32) -> u32 {
+    GLYPH_HEIGHT as u32 * scale
+}
+
+pub fn text_width(scale: u32, text: &str) -> u32 {
+    (text.chars().count() as i32 * GLYPH_ADVANCE - 1).max(0) as u32 * scale
+}
+
+pub fn render_text(canvas: &mut Canvas, x: i32, y: i32, scale: u32, color: Color, text: &str) {
+    canvas.set_draw_color(color);
+
+    let scale = scale as i32;
+
+    for (n, c) in text.chars().enumerate() {
+        let glyph_x = x + n as i32 * GLYPH_ADVANCE * scale;
+
+        for (j, bits) in glyph(c).iter().enumerate() {
+            for i in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - i)) != 0 {
+                    canvas
+                        .fill_rect(Rect::new(
+                            glyph_x + i * scale,
+                            y + j as i32 * scale,
+                            scale as u32,
+                            scale as u32,
+                        ))
+                        .unwrap();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use rand::RngCore;
+
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::UNKNOWN_PIECE;
+use crate::garbage::GARBAGE_GEN;
+use crate::world::WorldGenerator;
+
+const PREFIX: &str = "v115@";
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Fumen boards are always this wide.
+pub const FUMEN_WIDTH: usize = 10;
+/// Rows of the playfield.  There is a garbage row below them.
+const FIELD_ROWS: usize = 23;
+const FIELD_CELLS: usize = (FIELD_ROWS + 1) * FUMEN_WIDTH;
+
+/// Block types, indexed by fumen type.  0 is empty and 8 is gray.
+const TYPE_NAMES: [&str; 8] = ["", "I", "L", "O", "Z", "T", "J", "S"];
+const GRAY: u8 = 8;
+
+/// Rotation numbers as stored in an action.
+const REVERSE: u32 = 0;
+const RIGHT: u32 = 1;
+const SPAWN: u32 = 2;
+const LEFT: u32 = 3;
+
+/// Cells of a page, top row first and the garbage row last.
+type Field = [u8; FIELD_CELLS];
+
+/// Cells of a piece type in spawn orientation, relative to its centre, with
+/// y growing upwards.
+fn spawn_blocks(kind: u8) -> [(i32, i32); 4] {
+    match kind {
+        1 => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        2 => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        3 => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        4 => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+        5 => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        6 => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        _ => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+    }
+}
+
+fn blocks(kind: u8, rotation: u32) -> [(i32, i32); 4] {
+    spawn_blocks(kind).map(|(x, y)| match rotation {
+        RIGHT => (y, -x),
+        REVERSE => (-x, -y),
+        LEFT => (-y, x),
+        _ => (x, y),
+    })
+}
+
+/// Offset from a piece's centre to the position stored for it, which is off
+/// by one for some pieces for historical reasons.
+fn stored_offset(kind: u8, rotation: u32) -> (i32, i32) {
+    match (TYPE_NAMES.get(kind as usize).copied(), rotation) {
+        (Some("O"), LEFT) => (-1, 1),
+        (Some("O"), REVERSE) => (-1, 0),
+        (Some("O"), SPAWN) => (0, 1),
+        (Some("I"), REVERSE) => (-1, 0),
+        (Some("I"), LEFT) => (0, 1),
+        (Some("S"), SPAWN) => (0, 1),
+        (Some("S"), RIGHT) => (1, 0),
+        (Some("Z"), SPAWN) => (0, 1),
+        (Some("Z"), LEFT) => (-1, 0),
+        _ => (0, 0),
+    }
+}
+
+fn field_index(x: i32, y: i32) -> Option<usize> {
+    if (0..FUMEN_WIDTH as i32).contains(&x) && (0..FIELD_ROWS as i32).contains(&y) {
+        Some((FIELD_ROWS - 1 - y as usize) * FUMEN_WIDTH + x as usize)
+    } else {
+        None
+    }
+}
+
+fn push_value(out: &mut Vec<u8>, mut value: u32, chars: usize) {
+    for _ in 0..chars {
+        out.push(ALPHABET[(value % 64) as usize]);
+        value /= 64;
+    }
+}
+
+/// Fumen type of a cell in the world.
+fn cell_type(game: &Game, cell: Cell) -> u8 {
+    if cell.is_empty() {
+        return 0;
+    }
+
+    let name = match cell.piece {
+        UNKNOWN_PIECE => None,
+        piece => game.pieces.names.get(piece as usize),
+    };
+
+    name.and_then(|name| TYPE_NAMES.iter().position(|n| n == name))
+        .map_or(GRAY, |kind| kind as u8)
+}
+
+/// Encodes the top of the world and the active piece as a one-page fumen.
+/// As many rows are included as fit, as long as the active piece is among
+/// them.
+pub fn encode(game: &Game) -> Result<String, String> {
+    if game.width != FUMEN_WIDTH {
+        return Err(format!("fumen boards are {} cells wide", FUMEN_WIDTH));
+    }
+
+    let piece = game.effective_piece();
+    let top = usize::max(game.world.len(), game.y + piece.height);
+    let bottom = usize::min(top.saturating_sub(FIELD_ROWS), game.y);
+
+    let mut field = [0; FIELD_CELLS];
+    for (y, row) in game.world.iter().enumerate().skip(bottom).take(FIELD_ROWS) {
+        for (x, cell) in row.iter().enumerate() {
+            field[field_index(x as i32, (y - bottom) as i32).unwrap()] = cell_type(game, *cell);
+        }
+    }
+
+    let mut out = Vec::new();
+
+    // Each cell is stored as the difference from the previous page, which
+    // for the first page is empty, in runs of equal differences.
+    let mut start = 0;
+    while start < FIELD_CELLS {
+        let end = (start..FIELD_CELLS)
+            .find(|i| field[*i] != field[start])
+            .unwrap_or(FIELD_CELLS);
+        let diff = field[start] as u32 + 8;
+        push_value(
+            &mut out,
+            diff * FIELD_CELLS as u32 + (end - start - 1) as u32,
+            2,
+        );
+        start = end;
+    }
+
+    // An unchanged field is followed by the number of unchanged pages after
+    // it.
+    if field.iter().all(|cell| *cell == 0) {
+        push_value(&mut out, 0, 1);
+    }
+
+    // The active piece, if it is a tetromino, found by matching its cells
+    // against every rotation.
+    let kind = TYPE_NAMES
+        .iter()
+        .position(|name| *name == game.pieces.names[game.piece_index])
+        .filter(|kind| *kind > 0)
+        .map(|kind| kind as u8);
+
+    let mut cells = Vec::new();
+    for j in 0..piece.height {
+        for i in 0..piece.width {
+            if piece.cells[piece.height - j - 1][i] {
+                cells.push(((game.x + i) as i32, (game.y + j - bottom) as i32));
+            }
+        }
+    }
+    cells.sort();
+
+    let placement = kind.and_then(|kind| {
+        [SPAWN, RIGHT, REVERSE, LEFT].iter().find_map(|rotation| {
+            let mut shape = blocks(kind, *rotation);
+            shape.sort();
+            let (x, y) = (cells[0].0 - shape[0].0, cells[0].1 - shape[0].1);
+
+            let matches = cells.len() == 4
+                && shape
+                    .iter()
+                    .zip(cells.iter())
+                    .all(|((dx, dy), cell)| (x + dx, y + dy) == *cell);
+
+            if !matches {
+                return None;
+            }
+
+            let (ox, oy) = stored_offset(kind, *rotation);
+            field_index(x + ox, y + oy).map(|position| (kind, *rotation, position))
+        })
+    });
+
+    let (kind, rotation, position) = placement.unwrap_or((0, REVERSE, 0));
+
+    // Flags from the highest: not locked, comment, colorized, mirrored,
+    // raised.
+    let flags = 0b00100;
+    let action = ((flags * FIELD_CELLS as u32 + position as u32) * 4 + rotation) * 8 + kind as u32;
+    push_value(&mut out, action, 3);
+
+    // Long fumens are broken up like the editor does.
+    let data = String::from_utf8(out).unwrap();
+    let head = usize::min(data.len(), 42);
+    let mut text = format!("{}{}", PREFIX, &data[..head]);
+    for chunk in data.as_bytes()[head..].chunks(47) {
+        text.push('?');
+        text.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+
+    Ok(text)
+}
+
+struct Reader {
+    values: Vec<u32>,
+    pos: usize,
+}
+
+impl Reader {
+    fn poll(&mut self, chars: usize) -> Result<u32, String> {
+        if self.pos + chars > self.values.len() {
+            return Err("data ends in the middle of a page".to_string());
+        }
+
+        let value = self.values[self.pos..self.pos + chars]
+            .iter()
+            .rev()
+            .fold(0, |value, v| value * 64 + v);
+
+        self.pos += chars;
+        Ok(value)
+    }
+}
+
+/// Decodes the fields of all pages of a fumen.  Anything up to the version
+/// prefix, such as the address of a viewer, is skipped.
+fn decode(text: &str) -> Result<Vec<Field>, String> {
+    let data = match text.find(PREFIX) {
+        Some(start) => &text[start + PREFIX.len()..],
+        None => return Err(format!("not a fumen (only {} is understood)", PREFIX)),
+    };
+
+    let values = data
+        .chars()
+        .filter(|c| *c != '?')
+        .map(|c| {
+            ALPHABET
+                .iter()
+                .position(|a| *a as char == c)
+                .map(|v| v as u32)
+                .ok_or_else(|| format!("unexpected '{}' in fumen", c))
+        })
+        .collect::<Result<Vec<u32>, String>>()?;
+
+    let mut reader = Reader { values, pos: 0 };
+    let mut pages = Vec::new();
+    let mut prev: Field = [0; FIELD_CELLS];
+    let mut repeat = 0;
+
+    while reader.pos < reader.values.len() {
+        let mut field = prev;
+
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+
+            while index < FIELD_CELLS {
+                let run = reader.poll(2)?;
+                let diff = run / FIELD_CELLS as u32;
+                let count = (run % FIELD_CELLS as u32) as usize + 1;
+
+                if diff > 16 || index + count > FIELD_CELLS {
+                    return Err("bad field in fumen".to_string());
+                }
+
+                if diff == 8 && count == FIELD_CELLS {
+                    repeat = reader.poll(1)?;
+                }
+
+                for cell in field[index..index + count].iter_mut() {
+                    let kind = *cell as u32 + diff;
+                    if !(8..=16).contains(&kind) {
+                        return Err("bad field in fumen".to_string());
+                    }
+                    *cell = (kind - 8) as u8;
+                }
+
+                index += count;
+            }
+        }
+
+        let mut action = reader.poll(3)?;
+        let kind = (action % 8) as u8;
+        action /= 8;
+        let rotation = action % 4;
+        action /= 4;
+        let position = (action % FIELD_CELLS as u32) as i32;
+        action /= FIELD_CELLS as u32;
+        let rise = action & 1 != 0;
+        let mirror = action & 2 != 0;
+        let comment = action & 8 != 0;
+        let lock = action & 16 == 0;
+
+        // Comments are of no use here, but their length has to be known to
+        // get past them.
+        if comment {
+            let length = reader.poll(2)? as usize;
+            for _ in 0..length.div_ceil(4) {
+                reader.poll(5)?;
+            }
+        }
+
+        pages.push(field);
+
+        // The next page starts from the outcome of this one.
+        if lock {
+            if kind > 0 {
+                let (ox, oy) = stored_offset(kind, rotation);
+                let x = position % FUMEN_WIDTH as i32 - ox;
+                let y = FIELD_ROWS as i32 - 1 - position / FUMEN_WIDTH as i32 - oy;
+
+                for (dx, dy) in blocks(kind, rotation) {
+                    if let Some(index) = field_index(x + dx, y + dy) {
+                        field[index] = kind;
+                    }
+                }
+            }
+
+            let (play, garbage) = field.split_at(FIELD_ROWS * FUMEN_WIDTH);
+            let mut rows: Vec<&[u8]> = play
+                .chunks(FUMEN_WIDTH)
+                .filter(|row| row.contains(&0))
+                .collect();
+
+            if rise {
+                rows.push(garbage);
+            }
+
+            // A risen garbage row is used up.
+            let mut next = [0; FIELD_CELLS];
+            if !rise {
+                next[FIELD_ROWS * FUMEN_WIDTH..].copy_from_slice(garbage);
+            }
+
+            // Rows were gathered top first, so they are laid from the bottom.
+            for (k, row) in rows.iter().rev().take(FIELD_ROWS).enumerate() {
+                let start = (FIELD_ROWS - 1 - k) * FUMEN_WIDTH;
+                next[start..start + FUMEN_WIDTH].copy_from_slice(row);
+
+                if mirror {
+                    next[start..start + FUMEN_WIDTH].reverse();
+                }
+            }
+
+            field = next;
+        }
+
+        prev = field;
+    }
+
+    Ok(pages)
+}
+
+/// Rows of a fumen page, from the bottom up, made of initial garbage.  Pages
+/// are numbered from 1.
+pub fn decode_world(text: &str, page: usize) -> Result<Vec<Vec<Cell>>, String> {
+    let pages = decode(text)?;
+
+    let field = match page.checked_sub(1).and_then(|index| pages.get(index)) {
+        Some(field) => field,
+        None => return Err(format!("fumen has {} pages", pages.len())),
+    };
+
+    let mut world: Vec<Vec<Cell>> = field[..FIELD_ROWS * FUMEN_WIDTH]
+        .chunks(FUMEN_WIDTH)
+        .rev()
+        .map(|row| {
+            row.iter()
+                .map(|kind| match kind {
+                    0 => Cell::default(),
+                    _ => Cell {
+                        gen: GARBAGE_GEN,
+                        piece: UNKNOWN_PIECE,
+                    },
+                })
+                .collect()
+        })
+        .collect();
+
+    while world
+        .last()
+        .is_some_and(|row| row.iter().all(Cell::is_empty))
+    {
+        world.pop();
+    }
+
+    for (y, row) in world.iter().enumerate() {
+        if row.iter().all(Cell::is_empty) || row.iter().all(|cell| !cell.is_empty()) {
+            return Err(format!(
+                "row {} from the bottom needs holes and blocks",
+                y + 1
+            ));
+        }
+    }
+
+    Ok(world)
+}
+
+/// The field of a page of a fumen.
+pub struct Fumen {
+    pub data: String,
+    pub page: usize,
+}
+
+impl WorldGenerator for Fumen {
+    fn generate(&self, _: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+        if width != FUMEN_WIDTH {
+            return Err(format!("fumen boards are {} cells wide", FUMEN_WIDTH));
+        }
+
+        decode_world(&self.data, self.page)
+    }
+
+    fn describe(&self) -> String {
+        format!("fumen {} page {}", self.data, self.page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::garbage_row;
+    use crate::game::test_game;
+
+    #[test]
+    fn empty_field() {
+        let pages = decode("v115@vhAAgH").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].iter().all(|cell| *cell == 0));
+        assert!(decode_world("v115@vhAAgH", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn known_fields() {
+        // A single row with a hole on the right, from a viewer's address.
+        let world = decode_world("https://fumen.zui.jp/?v115@bhI8KeAgH", 1).unwrap();
+        assert!(world == vec![garbage_row("#########.")]);
+
+        // Four rows of a four-wide well.
+        let world = decode_world("v115@9gF8DeF8DeF8DeF8NeAgH", 1).unwrap();
+        assert!(world == vec![garbage_row("######...."); 4]);
+    }
+
+    #[test]
+    fn repeated_pages() {
+        // The second page is unchanged and repeated once more.
+        let text = "v115@bhI8KeAgHvhBAgHAgH";
+        let pages = decode(text).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages.iter().all(|page| *page == pages[0]));
+        assert!(decode_world(text, 4).is_err());
+    }
+
+    #[test]
+    fn rise() {
+        // The first page has a garbage row and rises, and so does the second.
+        let text = "v115@lhI8AeAYJvhAAYJvhAAgH";
+        let pages = decode(text).unwrap();
+        assert_eq!(pages.len(), 3);
+
+        assert!(decode_world(text, 1).unwrap().is_empty());
+        assert!(pages[0][FIELD_ROWS * FUMEN_WIDTH..].contains(&GRAY));
+
+        // The garbage row is used up by the rise.
+        assert!(decode_world(text, 2).unwrap() == vec![garbage_row("#########.")]);
+        assert!(pages[1][FIELD_ROWS * FUMEN_WIDTH..]
+            .iter()
+            .all(|cell| *cell == 0));
+
+        // Rising an empty garbage row still pushes the field up.
+        let bottom = field_index(0, 0).unwrap();
+        let above = field_index(0, 1).unwrap();
+        assert!(pages[2][bottom..bottom + FUMEN_WIDTH]
+            .iter()
+            .all(|cell| *cell == 0));
+        assert_eq!(
+            pages[2][above..above + FUMEN_WIDTH],
+            pages[1][bottom..bottom + FUMEN_WIDTH]
+        );
+    }
+
+    #[test]
+    fn mirror() {
+        let text = "v115@bhI8KeAQLvhAAgH";
+        assert!(decode_world(text, 1).unwrap() == vec![garbage_row("#########.")]);
+        assert!(decode_world(text, 2).unwrap() == vec![garbage_row(".#########")]);
+    }
+
+    #[test]
+    fn bad_fumens() {
+        assert!(decode("v110@vhAAgH").is_err());
+        assert!(decode("v115@vhAAg").is_err());
+        assert!(decode("v115@vhAA!H").is_err());
+        assert!(decode_world("v115@vhAAgH", 0).is_err());
+
+        // Rows have to have both holes and blocks.
+        assert!(decode_world("v115@bhJ8JeAgH", 1).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let world = vec![
+            garbage_row("####.#####"),
+            garbage_row("###..#####"),
+            garbage_row("#.########"),
+        ];
+
+        for piece_index in 0..7 {
+            for orient in 0..4 {
+                let mut game = test_game(FUMEN_WIDTH, world.clone());
+                game.piece_index = piece_index;
+                game.orient = orient;
+                game.x = 2;
+                game.y = 5;
+
+                let text = encode(&game).unwrap();
+                assert!(text.starts_with(PREFIX));
+                assert!(decode_world(&text, 1).unwrap() == world);
+
+                // The piece locks into the page after.
+                let locked = decode(&format!("{}vhAAgH", text)).unwrap()[1];
+                let piece = game.effective_piece();
+                let kind = TYPE_NAMES
+                    .iter()
+                    .position(|name| *name == game.pieces.names[piece_index])
+                    .unwrap() as u8;
+
+                for y in 0..FIELD_ROWS {
+                    for x in 0..FUMEN_WIDTH {
+                        let (i, j) = (x as isize - 2, y as isize - 5);
+                        let expected = if (0..piece.width as isize).contains(&i)
+                            && (0..piece.height as isize).contains(&j)
+                            && piece.cells[piece.height - 1 - j as usize][i as usize]
+                        {
+                            kind
+                        } else if y < world.len() && !world[y][x].is_empty() {
+                            GRAY
+                        } else {
+                            0
+                        };
+
+                        let index = field_index(x as i32, y as i32).unwrap();
+                        assert_eq!(locked[index], expected, "{} at {},{}", kind, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encode_needs_ten_columns() {
+        let mut game = test_game(FUMEN_WIDTH, Vec::new());
+        game.width = 8;
+        assert!(encode(&game).is_err());
+    }
+}
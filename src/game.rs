@@ -0,0 +1,587 @@
+use std::rc::Rc;
+
+use crate::garbage::GARBAGE_GEN;
+use crate::pieces::Piece;
+use crate::pieces::PieceSet;
+
+pub const DEFAULT_WIDTH: usize = 10;
+pub const MIN_WIDTH: usize = 4;
+pub const MAX_WIDTH: usize = 40;
+pub const START_HEIGHT: usize = 10;
+/// Rows of initial garbage between milestones.
+pub const DEPTH_MILESTONE: usize = 16;
+
+/// Piece type of cells which weren't placed as a known piece, such as the
+/// initial garbage.
+pub const UNKNOWN_PIECE: u8 = u8::MAX;
+
+/// A world position.  Empty cells have generation 0.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cell {
+    pub gen: u32,
+    pub piece: u8,
+}
+
+impl Cell {
+    pub fn is_empty(&self) -> bool {
+        self.gen == 0
+    }
+}
+
+/// When, how and in which play session a generation was placed.
+#[derive(Clone, Copy)]
+pub struct Generation {
+    /// Seconds since the Unix epoch, or 0 if unknown.
+    pub time: u64,
+    /// Play session number starting from 1, or 0 if unknown.
+    pub session: u32,
+    pub piece: u8,
+}
+
+impl Default for Generation {
+    fn default() -> Generation {
+        Generation {
+            time: 0,
+            session: 0,
+            piece: UNKNOWN_PIECE,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Game {
+    pub pieces: Rc<PieceSet>,
+    pub width: usize,
+    pub world: Vec<Vec<Cell>>,
+    pub next_gen: u32,
+    /// Indexed by generation number, so there is an entry for every
+    /// generation below `next_gen`.
+    pub history: Vec<Generation>,
+    /// Rows of initial garbage the world started with.
+    pub layer: usize,
+    pub session: u32,
+    /// Current wall-clock time in seconds, kept up to date by the frontend.
+    pub time: u64,
+    pub piece_index: usize,
+    pub next_piece_index: usize,
+    pub orient: u8,
+    pub y: usize,
+    pub x: usize,
+    /// The active piece's last move was a rotation, so that it can lock as a
+    /// T-spin.
+    pub rotated: bool,
+    /// Height of a fixed well to play in, instead of an endless tower.
+    pub well: Option<usize>,
+    /// Set when a piece spawns overlapping the stack (block out), or locks
+    /// entirely above the well (lock out).  Only happens in a well.
+    pub topped_out: bool,
+}
+
+impl Game {
+    pub fn effective_piece(&self) -> Piece {
+        self.pieces.effective_piece(self.piece_index, self.orient)
+    }
+
+    pub fn spawn_x(&self) -> usize {
+        (self.width - self.pieces.pieces[self.piece_index].width) / 2
+    }
+
+    /// Rows which still hold some of the initial garbage.  Rows are only
+    /// removed whole and nothing goes below the garbage, so these are always
+    /// the bottommost rows, and the shallowest of them is one below the count.
+    pub fn garbage_left(&self) -> usize {
+        self.world
+            .iter()
+            .take_while(|row| row.iter().any(|cell| cell.gen == GARBAGE_GEN))
+            .count()
+    }
+
+    /// Pieces spawn above the tower, or just above a well.
+    pub fn spawn_y(&self) -> usize {
+        match self.well {
+            Some(height) => height,
+            None => self.world.len() + START_HEIGHT,
+        }
+    }
+}
+
+pub fn move_piece(game: &mut Game, delta: isize) {
+    let piece = game.effective_piece();
+
+    if delta < 0 {
+        if game.x == 0 {
+            return;
+        }
+    } else {
+        if game.x + piece.width == game.width {
+            return;
+        }
+    };
+
+    for j in 0..piece.height {
+        if game.y + j < game.world.len() {
+            for i in 0..piece.width {
+                if piece.cells[piece.height - j - 1][i]
+                    && !game.world[game.y + j][((game.x + i) as isize + delta) as usize].is_empty()
+                {
+                    return;
+                }
+            }
+        }
+    }
+
+    game.x = (game.x as isize + delta) as usize;
+    game.rotated = false;
+}
+
+pub fn rotate_piece(game: &mut Game) {
+    let new_orient = (game.orient + 3) % 4;
+    let new_piece = game.pieces.effective_piece(game.piece_index, new_orient);
+
+    if game.x + new_piece.width > game.width {
+        return;
+    }
+
+    for j in 0..new_piece.height {
+        if game.y + j < game.world.len() {
+            for i in 0..new_piece.width {
+                if new_piece.cells[new_piece.height - j - 1][i]
+                    && !game.world[game.y + j][game.x + i].is_empty()
+                {
+                    return;
+                }
+            }
+        }
+    }
+
+    game.orient = new_orient;
+    game.rotated = true;
+}
+
+pub fn detect_collision(game: &Game, piece_y: usize, piece: &Piece) -> bool {
+    if piece_y == 0 {
+        true
+    } else {
+        for j in 0..piece.height {
+            if piece_y + j <= game.world.len() {
+                for i in 0..piece.width {
+                    if piece.cells[piece.height - j - 1][i]
+                        && !game.world[piece_y + j - 1][game.x + i].is_empty()
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// Things that happened in the world, reported so that the frontend can
+/// react to them without the engine knowing about rendering or files.
+pub enum GameEvent {
+    /// The piece was hard-dropped from `from_y` down to `to_y`.
+    Dropped {
+        piece_index: usize,
+        orient: u8,
+        x: usize,
+        from_y: usize,
+        to_y: usize,
+    },
+
+    /// Full rows were removed.  Each is listed as the index of the row which
+    /// ended up directly above the gap it left behind.
+    Cleared { rows: Vec<usize> },
+
+    /// A piece became part of the world at the given position.  The cells
+    /// which survived line clears are listed as (x, y) world positions.
+    Locked {
+        piece_index: usize,
+        orient: u8,
+        x: usize,
+        y: usize,
+        cells: Vec<(usize, usize)>,
+        /// The piece was rotated into place as a T-spin.
+        spin: bool,
+    },
+
+    /// The last lock was taken back, and the world replaced with an earlier
+    /// one.
+    Undone,
+
+    /// Rows of the initial garbage were cleared past a multiple of
+    /// DEPTH_MILESTONE.  The garbage is all gone when none are left.
+    Milestone { left: usize },
+}
+
+pub fn drop_distance(game: &Game) -> usize {
+    let piece = game.effective_piece();
+    let mut distance = 0;
+
+    while !detect_collision(game, game.y - distance, &piece) {
+        distance += 1;
+    }
+
+    distance
+}
+
+pub fn advance_game(game: &mut Game, events: &mut Vec<GameEvent>) -> bool {
+    let piece = game.effective_piece();
+    let collision = detect_collision(game, game.y, &piece);
+
+    if collision {
+        let spin = game.rotated && t_spin(game, &piece);
+
+        if game.well.is_some_and(|height| game.y >= height) {
+            game.topped_out = true;
+        }
+
+        let mut cells = Vec::new();
+
+        for j in 0..piece.height {
+            if game.y + j == game.world.len() {
+                game.world.push(vec![Cell::default(); game.width]);
+            };
+
+            let row = &mut game.world[game.y + j];
+
+            for (i, cell) in piece.cells[piece.height - j - 1].iter().enumerate() {
+                if *cell {
+                    row[game.x + i] = Cell {
+                        gen: game.next_gen,
+                        piece: game.piece_index as u8,
+                    };
+                    cells.push((game.x + i, game.y + j));
+                }
+            }
+        }
+
+        let mut removed = Vec::new();
+
+        for j in (0..piece.height).rev() {
+            if game.world[game.y + j].iter().all(|cell| !cell.is_empty()) {
+                removed.push((game.y + j, game.world.remove(game.y + j)));
+            }
+        }
+
+        if !removed.is_empty() {
+            let dug = removed
+                .iter()
+                .filter(|(_, row)| row.iter().any(|cell| cell.gen == GARBAGE_GEN))
+                .count();
+
+            // Rows were removed from the top down, so the rows below each one
+            // still shift it by their count.
+            let count = removed.len();
+            let rows = removed
+                .iter()
+                .enumerate()
+                .map(|(k, (y, _))| y - (count - 1 - k))
+                .collect();
+
+            events.push(GameEvent::Cleared { rows });
+
+            if dug > 0 {
+                let left = game.garbage_left();
+                let before = left + dug;
+
+                if left.div_ceil(DEPTH_MILESTONE) < before.div_ceil(DEPTH_MILESTONE) {
+                    events.push(GameEvent::Milestone { left });
+                }
+            }
+
+            cells = cells
+                .into_iter()
+                .filter(|(_, y)| removed.iter().all(|(removed_y, _)| removed_y != y))
+                .map(|(x, y)| {
+                    let below = removed.iter().filter(|(removed_y, _)| *removed_y < y);
+                    (x, y - below.count())
+                })
+                .collect();
+        }
+
+        events.push(GameEvent::Locked {
+            piece_index: game.piece_index,
+            orient: game.orient,
+            x: game.x,
+            y: game.y,
+            cells,
+            spin,
+        });
+
+        game.history.push(Generation {
+            time: game.time,
+            session: game.session,
+            piece: game.piece_index as u8,
+        });
+        game.next_gen += 1;
+    } else {
+        game.y -= 1;
+        game.rotated = false;
+    }
+
+    collision
+}
+
+/// True if the piece is a T tetromino with at least three of the four cells
+/// diagonal to its centre blocked by the world, the floor or the walls.
+fn t_spin(game: &Game, piece: &Piece) -> bool {
+    let mut cells = Vec::new();
+
+    for j in 0..piece.height {
+        for i in 0..piece.width {
+            if piece.cells[piece.height - j - 1][i] {
+                cells.push(((game.x + i) as isize, (game.y + j) as isize));
+            }
+        }
+    }
+
+    // The T is the only tetromino with a cell touching the three others.
+    let centre = cells.iter().find(|(x, y)| {
+        cells
+            .iter()
+            .filter(|(i, j)| (i - x).abs() + (j - y).abs() == 1)
+            .count()
+            == 3
+    });
+
+    let blocked = |x: isize, y: isize| {
+        x < 0
+            || x >= game.width as isize
+            || y < 0
+            || game
+                .world
+                .get(y as usize)
+                .is_some_and(|row| !row[x as usize].is_empty())
+    };
+
+    match centre {
+        Some((x, y)) if cells.len() == 4 => {
+            [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+                .iter()
+                .filter(|(dx, dy)| blocked(x + dx, y + dy))
+                .count()
+                >= 3
+        }
+
+        _ => false,
+    }
+}
+
+/// Makes the next piece active and queues another one.  Must be called after
+/// a piece has been locked.
+pub fn spawn_piece(game: &mut Game, next_piece_index: usize) {
+    game.piece_index = game.next_piece_index;
+    game.next_piece_index = next_piece_index;
+    game.orient = 0;
+    game.rotated = false;
+    game.x = game.spawn_x();
+    game.y = game.spawn_y();
+
+    if game.well.is_some() && overlaps(game) {
+        game.topped_out = true;
+    }
+}
+
+/// True if the piece is on top of cells in the world.
+fn overlaps(game: &Game) -> bool {
+    let piece = game.effective_piece();
+
+    (0..piece.height).any(|j| {
+        game.world.get(game.y + j).is_some_and(|row| {
+            (0..piece.width)
+                .any(|i| piece.cells[piece.height - j - 1][i] && !row[game.x + i].is_empty())
+        })
+    })
+}
+
+pub fn drop_piece(game: &mut Game, events: &mut Vec<GameEvent>) {
+    let from_y = game.y;
+    let distance = drop_distance(game);
+    game.y -= distance;
+
+    if distance > 0 {
+        game.rotated = false;
+    }
+
+    events.push(GameEvent::Dropped {
+        piece_index: game.piece_index,
+        orient: game.orient,
+        x: game.x,
+        from_y,
+        to_y: game.y,
+    });
+
+    advance_game(game, events);
+}
+
+/// A tetromino game on a world of initial garbage, before any piece is dealt.
+#[cfg(test)]
+pub fn test_game(width: usize, world: Vec<Vec<Cell>>) -> Game {
+    Game {
+        pieces: Rc::new(PieceSet::load("tetrominoes").unwrap()),
+        width,
+        world,
+        next_gen: GARBAGE_GEN + 1,
+        history: vec![Generation::default(); GARBAGE_GEN as usize + 1],
+        layer: 0,
+        session: 1,
+        time: 0,
+        piece_index: 0,
+        next_piece_index: 0,
+        orient: 0,
+        y: 0,
+        x: 0,
+        rotated: false,
+        well: None,
+        topped_out: false,
+    }
+}
+
+/// A row of initial garbage drawn like in a world file.
+#[cfg(test)]
+pub fn garbage_row(row: &str) -> Vec<Cell> {
+    row.chars()
+        .map(|c| match c {
+            '#' => Cell {
+                gen: GARBAGE_GEN,
+                piece: UNKNOWN_PIECE,
+            },
+            _ => Cell::default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game of the given rows, listed from the top down, with the named
+    /// piece placed on it.
+    fn placed(rows: &[&str], name: &str, orient: u8, x: usize, y: usize) -> Game {
+        let world = rows.iter().rev().map(|row| garbage_row(row)).collect();
+        let mut game = test_game(10, world);
+        game.piece_index = game.pieces.names.iter().position(|n| n == name).unwrap();
+        game.orient = orient;
+        game.x = x;
+        game.y = y;
+        game
+    }
+
+    fn spin(game: &mut Game) -> bool {
+        let mut events = Vec::new();
+        advance_game(game, &mut events);
+
+        events
+            .iter()
+            .find_map(|event| match event {
+                GameEvent::Locked { spin, .. } => Some(*spin),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn milestones(events: &[GameEvent]) -> Vec<usize> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Milestone { left } => Some(*left),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn t_spins() {
+        // A T pointing down into a slot with three of its corners covered.
+        let slot = ["###.......", "##...#####", "###.######"];
+
+        let mut game = placed(&slot, "T", 0, 2, 0);
+        game.rotated = true;
+        assert!(spin(&mut game));
+
+        // It has to have been rotated into place.
+        let mut game = placed(&slot, "T", 0, 2, 0);
+        assert!(!spin(&mut game));
+
+        // Two corners aren't enough.
+        let mut game = placed(&["##...#####", "###.######"], "T", 0, 2, 0);
+        game.rotated = true;
+        assert!(!spin(&mut game));
+    }
+
+    #[test]
+    fn lock_out() {
+        let stack = ["#########."; 4];
+
+        // Locking entirely above the well tops out.
+        let mut game = placed(&stack, "I", 0, 0, 4);
+        game.well = Some(4);
+        advance_game(&mut game, &mut Vec::new());
+        assert!(game.topped_out);
+
+        // But not in the tower, or in a deeper well.
+        for well in [None, Some(5)] {
+            let mut game = placed(&stack, "I", 0, 0, 4);
+            game.well = well;
+            advance_game(&mut game, &mut Vec::new());
+            assert!(!game.topped_out);
+        }
+    }
+
+    #[test]
+    fn block_out() {
+        // The I spawns in the middle columns, just above the well.
+        let stack = ["...#......", "#########.", "#########."];
+
+        let mut game = placed(&stack, "O", 0, 0, 0);
+        game.well = Some(2);
+        game.next_piece_index = 0;
+        spawn_piece(&mut game, 0);
+        assert!(game.topped_out);
+
+        // Nothing is in the way once the row above the well is clear of it.
+        let mut game = placed(&stack, "O", 0, 0, 0);
+        game.world[2] = garbage_row("#.........");
+        game.well = Some(2);
+        game.next_piece_index = 0;
+        spawn_piece(&mut game, 0);
+        assert!(!game.topped_out);
+
+        // A tower has no well to spawn in.
+        let mut game = placed(&stack, "O", 0, 0, 0);
+        game.next_piece_index = 0;
+        spawn_piece(&mut game, 0);
+        assert!(!game.topped_out);
+    }
+
+    #[test]
+    fn garbage_milestones() {
+        let dig = |layer: usize| {
+            let mut game = placed(&vec!["#########."; layer], "I", 1, 9, 0);
+            assert_eq!(game.garbage_left(), layer);
+
+            let mut events = Vec::new();
+            advance_game(&mut game, &mut events);
+            assert_eq!(game.garbage_left(), layer - 4);
+            milestones(&events)
+        };
+
+        // Only clearing past a multiple of DEPTH_MILESTONE counts.
+        assert_eq!(dig(DEPTH_MILESTONE + 1), vec![DEPTH_MILESTONE - 3]);
+        assert_eq!(dig(DEPTH_MILESTONE), Vec::<usize>::new());
+        assert_eq!(dig(4), vec![0]);
+
+        // Rows of placed pieces aren't garbage.
+        let mut game = placed(&["#########."; 4], "I", 1, 9, 0);
+        for cell in game.world.iter_mut().flatten() {
+            if !cell.is_empty() {
+                cell.gen += 1;
+            }
+        }
+        let mut events = Vec::new();
+        advance_game(&mut game, &mut events);
+        assert!(milestones(&events).is_empty());
+    }
+}
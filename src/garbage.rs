@@ -0,0 +1,78 @@
+use rand::Rng;
+use rand::RngCore;
+
+use crate::game::Cell;
+use crate::game::UNKNOWN_PIECE;
+use crate::world::WorldGenerator;
+
+/// Generation of the initial garbage.
+pub const GARBAGE_GEN: u32 = 1;
+/// Garbage rows of a new tower.  Towers from before the garbage was
+/// configurable all started with this many.
+pub const TOWER_ROWS: usize = 256;
+
+/// Random garbage rows.
+pub struct Garbage {
+    pub rows: usize,
+    /// Holes in each row.  Zero leaves every cell to chance.
+    pub holes: usize,
+    /// Percentage of the cells besides the holes which are filled.
+    pub density: u32,
+    /// Percentage of rows whose holes are placed anew instead of lining up
+    /// with those of the row below.
+    pub messiness: u32,
+}
+
+impl WorldGenerator for Garbage {
+    fn generate(&self, rng: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+        if self.holes >= width {
+            return Err(format!(
+                "{} holes don't leave room for garbage on a board {} cells wide",
+                self.holes, width
+            ));
+        }
+
+        if self.holes == 0 && self.density >= 100 {
+            return Err("garbage without holes needs a density below 100".to_string());
+        }
+
+        let mut world = Vec::with_capacity(self.rows);
+        let mut holes: Vec<usize> = Vec::new();
+
+        for _ in 0..self.rows {
+            if holes.is_empty() || rng.gen_range(0, 100) < self.messiness {
+                holes = rand::seq::sample_indices(rng, width, self.holes);
+            }
+
+            loop {
+                let row: Vec<Cell> = (0..width)
+                    .map(|x| {
+                        if !holes.contains(&x) && rng.gen_range(0, 100) < self.density {
+                            Cell {
+                                gen: GARBAGE_GEN,
+                                piece: UNKNOWN_PIECE,
+                            }
+                        } else {
+                            Cell::default()
+                        }
+                    })
+                    .collect();
+
+                let blocks = row.iter().filter(|cell| !cell.is_empty()).count();
+                if blocks > 0 && blocks < width {
+                    world.push(row);
+                    break;
+                }
+            }
+        }
+
+        Ok(world)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "garbage {} holes {} density {} messiness {}",
+            self.rows, self.holes, self.density, self.messiness
+        )
+    }
+}
@@ -0,0 +1,119 @@
+use crate::game::START_HEIGHT;
+use crate::pieces::MAX_PIECE_SIZE;
+use crate::render::Canvas;
+
+/// Preferred size of a board cell, in points.
+pub const CELL_SIZE: usize = 32;
+pub const GAP_WIDTH: usize = 4;
+pub const PIECE_POS: usize = 3;
+/// Row on screen where the surface of the world settles.
+pub const SURFACE_POS: usize = PIECE_POS + 4 + START_HEIGHT;
+pub const NEXT_ZOOM: usize = 2;
+pub const WORLD_ZOOM: usize = 5;
+/// Window height in cells.
+pub const WIN_ROWS: usize = 30;
+/// Camera level which rests the bottom of a fixed well one row above the
+/// bottom of the window.
+pub const WELL_CAMERA_LEVEL: usize = WIN_ROWS - SURFACE_POS - 1;
+const MIN_CELL_SIZE: i32 = 4;
+
+/// Width of the board and the side panel, in cells.  The side panel fits
+/// both the next piece and the minimap.
+fn width_in_cells(width: usize) -> f64 {
+    let next = MAX_PIECE_SIZE as f64 / NEXT_ZOOM as f64;
+    let panel = f64::max(next, width as f64 / WORLD_ZOOM as f64);
+    width as f64 + GAP_WIDTH as f64 / CELL_SIZE as f64 + panel
+}
+
+/// Initial window size in points, shrunk to fit within the given bounds.
+pub fn window_size(width: usize, bounds: (u32, u32)) -> (u32, u32) {
+    let units = width_in_cells(width);
+    let fit = f64::min(bounds.0 as f64 / units, bounds.1 as f64 / WIN_ROWS as f64);
+    let cell = f64::max(
+        MIN_CELL_SIZE as f64,
+        f64::min(CELL_SIZE as f64, fit).floor(),
+    );
+
+    (
+        (units * cell).ceil() as u32,
+        (WIN_ROWS as f64 * cell) as u32,
+    )
+}
+
+/// Screen geometry in drawable pixels, scaled to fit the window.
+pub struct Layout {
+    /// Size of a board cell.
+    pub cell: i32,
+    /// Left edge of the board.
+    pub left: i32,
+    pub board_width: i32,
+    pub gap: i32,
+    /// Left edge of the side panel.
+    pub panel_x: i32,
+    pub next_cell: i32,
+    pub minimap_y: i32,
+    pub minimap_cell: i32,
+    pub minimap_width: i32,
+    /// Screen position where the surface of the world settles.
+    pub surface_y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Drawable pixels per window coordinate unit.
+    pub pixel_ratio: f64,
+}
+
+impl Layout {
+    pub fn new(width: usize, drawable: (u32, u32), window: (u32, u32)) -> Layout {
+        let fit = f64::min(
+            drawable.0 as f64 / width_in_cells(width),
+            drawable.1 as f64 / WIN_ROWS as f64,
+        );
+        let cell = i32::max(MIN_CELL_SIZE, fit as i32);
+
+        let gap = i32::max(1, cell * GAP_WIDTH as i32 / CELL_SIZE as i32);
+        let board_width = width as i32 * cell;
+        let next_cell = cell / NEXT_ZOOM as i32;
+        let minimap_cell = i32::max(1, cell / WORLD_ZOOM as i32);
+        let minimap_width = width as i32 * minimap_cell;
+        let next_size = MAX_PIECE_SIZE as i32 * next_cell;
+        let panel_width = i32::max(next_size, minimap_width);
+        let left = i32::max(0, (drawable.0 as i32 - board_width - gap - panel_width) / 2);
+
+        Layout {
+            cell,
+            left,
+            board_width,
+            gap,
+            panel_x: left + board_width + gap,
+            next_cell,
+            minimap_y: gap + next_size + gap + PIECE_POS as i32 * cell / WORLD_ZOOM as i32,
+            minimap_cell,
+            minimap_width,
+            surface_y: drawable.1 as i32 * SURFACE_POS as i32 / WIN_ROWS as i32,
+            width: drawable.0 as i32,
+            height: drawable.1 as i32,
+            pixel_ratio: drawable.0 as f64 / f64::max(1.0, window.0 as f64),
+        }
+    }
+
+    pub fn for_canvas(canvas: &Canvas, width: usize) -> Layout {
+        Layout::new(width, canvas.output_size().unwrap(), canvas.window().size())
+    }
+
+    /// Pixel size of the HUD font.
+    pub fn text_scale(&self) -> u32 {
+        u32::max(1, self.cell as u32 / 16)
+    }
+
+    pub fn text_margin(&self) -> i32 {
+        2 * self.text_scale() as i32
+    }
+
+    /// Converts window coordinates from mouse events to drawable pixels.
+    pub fn to_pixels(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            (x as f64 * self.pixel_ratio) as i32,
+            (y as f64 * self.pixel_ratio) as i32,
+        )
+    }
+}
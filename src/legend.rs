@@ -0,0 +1,108 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::font::render_text;
+use crate::font::text_height;
+use crate::font::text_width;
+use crate::game::Game;
+use crate::layout::Layout;
+use crate::render::gradient_color;
+use crate::render::session_color;
+use crate::render::AgeRange;
+use crate::render::AgeScheme;
+use crate::render::Canvas;
+use crate::render::Style;
+
+/// Formats seconds since the Unix epoch as a UTC date and time of day.
+pub fn format_time(time: u64) -> String {
+    let minutes = time / 60 % (24 * 60);
+    format!(
+        "{} {:02}:{:02}",
+        format_date(time),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Formats seconds since the Unix epoch as a UTC date.
+pub fn format_date(time: u64) -> String {
+    // Days to civil date conversion from Howard Hinnant's date algorithms.
+    let days = (time / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Explains the age colours: a strip of the gradient, labelled with what its
+/// ends mean under the current scheme.
+pub fn render_legend(canvas: &mut Canvas, layout: &Layout, style: &Style, game: &Game) {
+    let scale = layout.text_scale();
+    let margin = layout.text_margin();
+    let line_height = text_height(scale) as i32 + margin;
+    let x = layout.left + margin;
+    let y = margin + line_height;
+    let width = layout.board_width - 2 * margin;
+    let range = AgeRange::new(game);
+    let sessions = game.session.max(1);
+
+    let (old, new) = match style.age_scheme {
+        AgeScheme::Linear => ("GEN 1".to_string(), format!("GEN {}", game.next_gen - 1)),
+
+        AgeScheme::Log => (
+            format!("LOG AGE {}", game.next_gen.saturating_sub(2)),
+            "0".to_string(),
+        ),
+
+        AgeScheme::Time => (format_date(range.first_time), "NOW".to_string()),
+
+        AgeScheme::Session => ("SESSION 1".to_string(), format!("SESSION {}", sessions)),
+    };
+
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 127));
+    canvas
+        .fill_rect(Rect::new(
+            layout.left,
+            y - margin,
+            layout.board_width as u32,
+            (2 * line_height + margin) as u32,
+        ))
+        .unwrap();
+
+    for i in 0..width {
+        let t = i as f64 / (width - 1).max(1) as f64;
+
+        let color = match style.age_scheme {
+            AgeScheme::Session => {
+                let session = 1 + (t * (sessions - 1) as f64).round() as u32;
+                session_color(&style.theme, session, sessions)
+            }
+
+            _ => gradient_color(&style.theme, t),
+        };
+
+        canvas.set_draw_color(color);
+        canvas
+            .fill_rect(Rect::new(x + i, y, 1, (line_height - margin) as u32))
+            .unwrap();
+    }
+
+    let label_y = y + line_height;
+    let white = Color::RGB(255, 255, 255);
+
+    render_text(canvas, x, label_y, scale, white, &old);
+    render_text(
+        canvas,
+        x + width - text_width(scale, &new) as i32,
+        label_y,
+        scale,
+        white,
+        &new,
+    );
+}
@@ -1,13 +1,38 @@
 extern crate rand;
 extern crate sdl2;
 
-use std::f64;
-use std::fs::rename;
-use std::fs::File;
-use std::io::Read;
-use std::io::Write;
+mod anim;
+mod bot;
+mod camera;
+mod explore;
+mod font;
+mod fumen;
+mod game;
+mod garbage;
+mod layout;
+mod legend;
+mod menu;
+mod overlay;
+mod pieces;
+mod play;
+mod protocol;
+mod puzzle;
+mod records;
+mod render;
+mod replay;
+mod search;
+mod settings;
+mod state;
+mod theme;
+mod timelapse;
+mod world;
+
+use std::process::exit;
+use std::rc::Rc;
+use std::thread::sleep;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use rand::Rng;
 use sdl2::event::Event;
@@ -15,656 +40,593 @@ use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::BlendMode;
-
-const GAME_WIDTH: usize = 10;
-const CELL_SIZE: usize = 32;
-const GAP_WIDTH: usize = 4;
-const PIECE_POS: usize = 3;
-const START_HEIGHT: usize = 10;
-const NEXT_ZOOM: usize = 2;
-const WORLD_ZOOM: usize = 5;
-const CELL_BORDER: i32 = 1;
-const WIN_WIDTH: usize = GAME_WIDTH * CELL_SIZE + GAP_WIDTH + 64;
-const WIN_HEIGHT: usize = 960;
-
-static BACKGROUND_COLOR: Color = Color {
-    r: 0,
-    g: 0,
-    b: 0,
-    a: 255,
-};
-
-struct Piece {
-    width: usize,
-    height: usize,
-    cells: [[bool; 4]; 4],
+use sdl2::sys::SDL_RendererFlags;
+use sdl2::video::FullscreenType;
+
+use crate::anim::Animations;
+use crate::bot::Autoplay;
+use crate::bot::Bot;
+use crate::bot::Hint;
+use crate::bot::Player;
+use crate::camera::Camera;
+use crate::explore::Explore;
+use crate::font::render_text;
+use crate::font::text_height;
+use crate::fumen::encode;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::game::Generation;
+use crate::game::DEFAULT_WIDTH;
+use crate::game::UNKNOWN_PIECE;
+use crate::layout::window_size;
+use crate::layout::Layout;
+use crate::layout::WELL_CAMERA_LEVEL;
+use crate::menu::ThemeMenu;
+use crate::overlay::render_overlay;
+use crate::pieces::PieceSet;
+use crate::play::format_ticks;
+use crate::play::Goal;
+use crate::play::Input;
+use crate::play::Mode;
+use crate::play::Play;
+use crate::play::Randomizer;
+use crate::play::Stats;
+use crate::play::SPLIT_LINES;
+use crate::play::SPLIT_SECONDS;
+use crate::play::TICK_RATE;
+use crate::play::WELL_HEIGHT;
+use crate::protocol::serve;
+use crate::protocol::External;
+use crate::puzzle::Pack;
+use crate::puzzle::Progress;
+use crate::records::Category;
+use crate::records::Records;
+use crate::render::render_game;
+use crate::render::render_progress;
+use crate::render::toggle_fullscreen;
+use crate::render::Scene;
+use crate::render::Style;
+use crate::replay::Action;
+use crate::replay::Playback;
+use crate::replay::Recorder;
+use crate::replay::Replay;
+use crate::settings::Settings;
+use crate::state::append_journal;
+use crate::state::load_game;
+use crate::state::load_journal;
+use crate::state::save_game;
+use crate::state::start_journal;
+use crate::state::unwind_journal;
+use crate::state::JournalEntry;
+use crate::theme::Theme;
+use crate::timelapse::run_timelapse;
+
+const MAX_CATCHUP_TICKS: u32 = 10;
+/// Ticks from one of the bot's inputs to the next, slow enough to follow
+/// but quick enough to tuck a piece before gravity locks it.
+const BOT_INPUT_TICKS: u32 = 3;
+
+/// One-line account of the game so far.
+fn summary(stats: &Stats) -> String {
+    format!(
+        "PIECES {} LINES {} SCORE {}{}",
+        stats.pieces,
+        stats.lines,
+        stats.score,
+        if stats.assisted() { " ASSISTED" } else { "" }
+    )
 }
 
-static PIECES: [Piece; 7] = [
-    Piece {
-        width: 4,
-        height: 1,
-        cells: [
-            [true, true, true, true],
-            [false, false, false, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 3,
-        height: 2,
-        cells: [
-            [true, true, true, false],
-            [false, false, true, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 3,
-        height: 2,
-        cells: [
-            [true, true, true, false],
-            [true, false, false, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 2,
-        height: 2,
-        cells: [
-            [true, true, false, false],
-            [true, true, false, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 3,
-        height: 2,
-        cells: [
-            [false, true, true, false],
-            [true, true, false, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 3,
-        height: 2,
-        cells: [
-            [true, true, true, false],
-            [false, true, false, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-    Piece {
-        width: 3,
-        height: 2,
-        cells: [
-            [true, true, false, false],
-            [false, true, true, false],
-            [false, false, false, false],
-            [false, false, false, false],
-        ],
-    },
-];
-
-static COLORS: [Color; 7] = [
-    Color {
-        r: 0,
-        g: 255,
-        b: 255,
-        a: 255,
-    },
-    Color {
-        r: 0,
-        g: 0,
-        b: 255,
-        a: 255,
-    },
-    Color {
-        r: 255,
-        g: 165,
-        b: 0,
-        a: 255,
-    },
-    Color {
-        r: 255,
-        g: 255,
-        b: 0,
-        a: 255,
-    },
-    Color {
-        r: 0,
-        g: 255,
-        b: 0,
-        a: 255,
-    },
-    Color {
-        r: 170,
-        g: 0,
-        b: 255,
-        a: 255,
-    },
-    Color {
-        r: 255,
-        g: 0,
-        b: 0,
-        a: 255,
-    },
-];
-
-struct Game {
-    world: Vec<[u32; GAME_WIDTH]>,
-    next_gen: u32,
-    piece_index: usize,
-    next_piece_index: usize,
-    orient: u8,
-    y: usize,
-    x: usize,
+/// Seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
 }
 
-impl Game {
-    fn effective_piece(&self) -> Piece {
-        effective_piece(self.piece_index, self.orient)
-    }
-}
+pub fn main() {
+    let settings = Settings::from_args();
 
-fn effective_piece(index: usize, orient: u8) -> Piece {
-    let orig_piece = &PIECES[index];
-    let mut width = orig_piece.width;
-    let mut height = orig_piece.height;
-    let mut cells = orig_piece.cells;
-
-    for _ in 0..orient {
-        let orig = cells;
-        cells = [
-            [orig[0][3], orig[1][3], orig[2][3], orig[3][3]],
-            [orig[0][2], orig[1][2], orig[2][2], orig[3][2]],
-            [orig[0][1], orig[1][1], orig[2][1], orig[3][1]],
-            [orig[0][0], orig[1][0], orig[2][0], orig[3][0]],
-        ];
-
-        while cells[0].iter().all(|cell| !*cell) {
-            let orig = cells;
-            cells = [orig[1], orig[2], orig[3], [false; 4]];
-        }
+    let bot = Bot {
+        weights: settings.ai_weights,
+        lookahead: settings.ai_lookahead,
+    };
 
-        while !cells[0][0] && !cells[1][0] && !cells[2][0] && !cells[3][0] {
-            let orig = cells;
-            cells = [
-                [orig[0][1], orig[0][2], orig[0][3], false],
-                [orig[1][1], orig[1][2], orig[1][3], false],
-                [orig[2][1], orig[2][2], orig[2][3], false],
-                [orig[3][1], orig[3][2], orig[3][3], false],
-            ];
+    if let Some(link) = &settings.serve {
+        if let Err(e) = serve(link, &bot) {
+            eprintln!("infinitris: {}", e);
+            exit(1);
         }
-
-        let temp = height;
-        height = width;
-        width = temp;
+        return;
     }
 
-    Piece {
-        width: width,
-        height: height,
-        cells: cells,
-    }
-}
+    let replay = settings
+        .replay
+        .as_ref()
+        .map(|path| match Replay::load(path) {
+            Ok(replay) => replay,
 
-fn move_piece(game: &mut Game, delta: isize) {
-    let piece = game.effective_piece();
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
+            }
+        });
+
+    let pack = settings
+        .puzzle
+        .as_ref()
+        .filter(|_| replay.is_none())
+        .map(|name| match Pack::load(name) {
+            Ok(pack) => pack,
+
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
+            }
+        });
 
-    if delta < 0 {
-        if game.x == 0 {
-            return;
-        }
-    } else {
-        if game.x + piece.width == GAME_WIDTH {
-            return;
-        }
+    // A replay is played with the pieces it was recorded with, and puzzles
+    // with the pieces of their pack.
+    let pieces_name = match &replay {
+        Some(replay) => &replay.pieces,
+        None => &settings.pieces,
     };
 
-    for j in 0..piece.height {
-        if game.y + j < game.world.len() {
-            for i in 0..piece.width {
-                if piece.cells[piece.height - j - 1][i]
-                    && game.world[game.y + j][((game.x + i) as isize + delta) as usize] != 0
-                {
-                    return;
-                }
+    let pieces = match &pack {
+        Some(pack) => pack.pieces.clone(),
+
+        None => match PieceSet::load(pieces_name) {
+            Ok(pieces) => Rc::new(pieces),
+
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
             }
+        },
+    };
+
+    let theme = match Theme::load(&settings.theme) {
+        Ok(theme) => theme,
+
+        Err(e) => {
+            eprintln!("infinitris: {}", e);
+            exit(1);
         }
-    }
+    };
+    let themes = Theme::load_all(&theme);
+
+    let mode = match &replay {
+        Some(replay) => match (replay.goal, replay.well) {
+            (Some(Goal::Lines(_)), _) => Mode::Sprint,
+            (Some(Goal::Time(_)), _) => Mode::Ultra,
+            (Some(Goal::Depth(_)), _) => Mode::Dig,
+            (None, Some(_)) => Mode::Classic,
+
+            // Puzzles aren't recorded.
+            _ => Mode::Infinite,
+        },
+        None => settings.mode,
+    };
 
-    game.x = (game.x as isize + delta) as usize;
-}
+    // Classic games, replays and bots leave the tower alone.
+    let persistent = mode == Mode::Infinite && replay.is_none() && !settings.ai;
 
-fn rotate_piece(game: &mut Game) {
-    let new_orient = (game.orient + 3) % 4;
-    let new_piece = effective_piece(game.piece_index, new_orient);
+    let piece_count = pieces.len();
+    let seed = match &replay {
+        Some(replay) => replay.seed,
+        None => settings.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
 
-    if game.x + new_piece.width > GAME_WIDTH {
-        return;
-    }
+    let mut game = Game {
+        pieces,
+        width: match &pack {
+            Some(pack) => pack.width,
+            None => settings.width.unwrap_or(DEFAULT_WIDTH),
+        },
+        world: Vec::new(),
+        next_gen: 1,
+        history: vec![Generation::default()],
+        layer: 0,
+        session: 0,
+        time: unix_time(),
+        piece_index: 0,
+        next_piece_index: 0,
+        orient: 0,
+        y: 0,
+        x: 0,
+        rotated: false,
+        well: match mode {
+            Mode::Infinite | Mode::Dig => None,
+            _ => Some(WELL_HEIGHT),
+        },
+        topped_out: false,
+    };
 
-    for j in 0..new_piece.height {
-        if game.y + j < game.world.len() {
-            for i in 0..new_piece.width {
-                if new_piece.cells[new_piece.height - j - 1][i]
-                    && game.world[game.y + j][game.x + i] != 0
-                {
-                    return;
-                }
-            }
+    let loaded = match &replay {
+        Some(replay) => {
+            game.width = replay.width;
+            game.world = replay.world.clone();
+            game.next_gen = replay.next_gen;
+            game.history
+                .resize(replay.next_gen as usize, Generation::default());
+            game.well = replay.well;
+            game.layer = game.garbage_left();
+            true
         }
-    }
 
-    game.orient = new_orient;
-}
+        None if mode != Mode::Infinite => false,
+        None => match load_game(&mut game) {
+            Ok(loaded) => loaded,
 
-fn detect_collision(game: &Game, piece_y: usize, piece: &Piece) -> bool {
-    if piece_y == 0 {
-        true
-    } else {
-        for j in 0..piece.height {
-            if piece_y + j <= game.world.len() {
-                for i in 0..piece.width {
-                    if piece.cells[piece.height - j - 1][i]
-                        && game.world[piece_y + j - 1][game.x + i] != 0
-                    {
-                        return true;
-                    }
-                }
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
             }
-        }
+        },
+    };
 
-        false
+    if settings.timelapse && !loaded {
+        eprintln!("infinitris: there is no tower to play back");
+        exit(1);
     }
-}
 
-fn advance_game(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, game: &mut Game) -> bool {
-    let piece = game.effective_piece();
-    let collision = detect_collision(game, game.y, &piece);
+    // Every run of the game is a new session.
+    game.session = game.history.iter().map(|g| g.session).max().unwrap() + 1;
 
-    if collision {
-        for j in 0..piece.height {
-            if game.y + j == game.world.len() {
-                game.world.push([0; GAME_WIDTH]);
-            };
+    let mut progress = match &pack {
+        Some(_) => match Progress::load() {
+            Ok(progress) => progress,
 
-            let row = &mut game.world[game.y + j];
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
+            }
+        },
 
-            for (i, cell) in piece.cells[piece.height - j - 1].iter().enumerate() {
-                if *cell {
-                    row[game.x + i] = game.next_gen;
+        None => Progress::default(),
+    };
+
+    // A pack picks up where it was left.
+    let mut puzzle_index = pack
+        .as_ref()
+        .map_or(0, |pack| progress.first_unsolved(pack));
+
+    if loaded {
+        if settings.width.is_some_and(|width| width != game.width) {
+            eprintln!(
+                "infinitris: keeping the existing tower's width {}",
+                game.width
+            );
+        }
+    } else {
+        game.world = match &pack {
+            Some(pack) => pack.puzzles[puzzle_index].world.clone(),
+
+            None => match settings
+                .world()
+                .generate(&mut rand::thread_rng(), game.width)
+            {
+                Ok(world) => world,
+
+                Err(e) => {
+                    eprintln!("infinitris: {}", e);
+                    exit(1);
                 }
-            }
+            },
+        };
+        game.layer = game.garbage_left();
+
+        if game.well.is_some_and(|height| game.world.len() >= height) {
+            eprintln!(
+                "infinitris: {} rows don't fit in a well {} rows high",
+                game.world.len(),
+                WELL_HEIGHT
+            );
+            exit(1);
         }
 
-        for j in (0..piece.height).rev() {
-            let mut gaps = false;
+        // The first generation belongs to the garbage even if there is none,
+        // so that no piece is mistaken for it.
+        game.history.push(Generation {
+            time: game.time,
+            session: game.session,
+            piece: UNKNOWN_PIECE,
+        });
+        game.next_gen = 2;
+    }
 
-            for cell in game.world[game.y + j].iter() {
-                if *cell == 0 {
-                    gaps = true;
-                    break;
-                }
-            }
+    let goal = match (&replay, mode) {
+        (Some(replay), _) => replay.goal,
+        (None, Mode::Sprint) => Some(Goal::Lines(settings.lines)),
+        (None, Mode::Ultra) => Some(Goal::Time(settings.time)),
 
-            if !gaps {
-                game.world.remove(game.y + j);
-                render_game(canvas, game);
+        (None, Mode::Dig) => {
+            let rows = game.layer;
+            let depth = settings.depth.unwrap_or(rows);
+
+            if rows == 0 {
+                eprintln!("infinitris: there is no garbage to dig");
+                exit(1);
             }
-        }
 
-        game.next_gen += 1;
-        game.y = game.world.len() + START_HEIGHT;
-        game.x = (GAME_WIDTH - 4) / 2;
-        game.orient = 0;
-
-        let mut file = File::create(".infinitris.state.tmp").unwrap();
-        file.write(&[1, 0, 0, 0, 0, 0, 0, 0]).unwrap();
-        for row in game.world.iter() {
-            for cell in row.iter() {
-                file.write(&[
-                    ((*cell >> 24) & 0xff) as u8,
-                    ((*cell >> 16) & 0xff) as u8,
-                    ((*cell >> 8) & 0xff) as u8,
-                    (*cell & 0xff) as u8,
-                ])
-                .unwrap();
+            if depth > rows {
+                eprintln!(
+                    "infinitris: --depth is deeper than the {} rows of garbage",
+                    rows
+                );
+                exit(1);
             }
+
+            Some(Goal::Depth((rows - depth) as u32))
         }
-        file.flush().unwrap();
-        rename(".infinitris.state.tmp", "infinitris.state").unwrap();
-    } else {
-        game.y -= 1;
-    }
 
-    collision
-}
+        _ => None,
+    };
 
-fn drop_piece(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, game: &mut Game) {
-    while !advance_game(canvas, game) {
-        render_game(canvas, game);
+    if game.pieces.max_size() > game.width {
+        eprintln!(
+            "infinitris: {} don't fit on a board {} cells wide",
+            game.pieces.name, game.width
+        );
+        exit(1);
     }
-}
 
-fn render_game(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, game: &Game) {
-    canvas.set_draw_color(BACKGROUND_COLOR);
-    canvas.clear();
+    let journal = load_journal();
 
-    let piece = game.effective_piece();
-    let piece_x = game.x * CELL_SIZE;
-    let piece_y = (PIECE_POS + 4 - piece.height) * CELL_SIZE;
-
-    let world_y = ((PIECE_POS + 4) * CELL_SIZE) as i32
-        + (game.y as i32 - game.world.len() as i32) * CELL_SIZE as i32;
+    // The journal is started over whenever it can't be replayed up to the
+    // current tower.
+    let journaled = loaded && journal.as_ref().is_some_and(|j| j.matches(&game));
+    if persistent && !settings.timelapse && !journaled {
+        start_journal(&game);
+    }
 
-    for (j, row) in game.world.iter().rev().enumerate() {
-        for (i, cell) in row.iter().enumerate() {
-            if *cell != 0 {
-                let age = *cell as f64 / game.next_gen as f64;
+    let undo_depth = match &replay {
+        Some(replay) => replay.undo_depth,
+        None => settings.undo_depth,
+    };
 
-                let color = Color::RGB(
-                    (64.0 + (0.5 * f64::consts::PI * age).sin() * 127.0) as u8,
-                    (160.0 * age + 32.0 * (64.0 * f64::consts::PI * age).sin()) as u8,
-                    (64.0 + (0.5 * f64::consts::PI * age).cos() * 127.0) as u8,
-                );
+    // A game in a well can be started over from scratch, or from the board of
+    // another puzzle.
+    let start = (mode != Mode::Infinite).then(|| game.clone());
 
-                render_block(
-                    canvas,
-                    CELL_SIZE as u32,
-                    (i * CELL_SIZE) as i32,
-                    world_y + (j * CELL_SIZE) as i32,
-                    color,
-                );
+    let new_play = |game: Game, seed: u64, puzzle_index: usize| {
+        let mut play = match &pack {
+            Some(pack) => pack.puzzles[puzzle_index].play(game, seed, undo_depth),
 
-                render_block(
-                    canvas,
-                    (CELL_SIZE / WORLD_ZOOM) as u32,
-                    (GAME_WIDTH * CELL_SIZE + GAP_WIDTH + i * CELL_SIZE / WORLD_ZOOM) as i32,
-                    (GAP_WIDTH
-                        + 4 * CELL_SIZE / NEXT_ZOOM
-                        + GAP_WIDTH
-                        + (PIECE_POS + j) * CELL_SIZE / WORLD_ZOOM) as i32,
-                    color,
-                );
+            None => {
+                let mut play = Play::new(game, Randomizer::new(seed, piece_count), undo_depth);
+                play.goal = goal;
+                play
             }
-        }
-    }
+        };
 
-    canvas.set_draw_color(Color::RGB(31, 31, 31));
+        play.deal();
+        play
+    };
 
-    canvas
-        .fill_rect(Rect::new(
-            0,
-            world_y + (game.world.len() * CELL_SIZE) as i32,
-            (GAME_WIDTH * CELL_SIZE) as u32,
-            WIN_HEIGHT as u32,
-        ))
-        .unwrap();
+    let mut play = new_play(game, seed, puzzle_index);
 
-    canvas
-        .fill_rect(Rect::new(
-            (GAME_WIDTH * CELL_SIZE) as i32,
-            0,
-            GAP_WIDTH as u32,
-            WIN_HEIGHT as u32,
-        ))
-        .unwrap();
+    let mut playback = replay.map(Playback::new);
 
-    canvas
-        .fill_rect(Rect::new(
-            (GAME_WIDTH * CELL_SIZE + GAP_WIDTH) as i32,
-            (GAP_WIDTH
-                + 4 * CELL_SIZE / NEXT_ZOOM
-                + GAP_WIDTH
-                + (PIECE_POS + game.world.len()) * CELL_SIZE / WORLD_ZOOM) as i32,
-            (GAME_WIDTH * CELL_SIZE / WORLD_ZOOM) as u32,
-            WIN_HEIGHT as u32,
-        ))
-        .unwrap();
+    let mut autoplay = settings.ai.then(|| {
+        let player: Box<dyn Player> = match &settings.bot {
+            Some(link) => match External::connect(link, &play.game) {
+                Ok(external) => Box::new(external),
 
-    let mut shadow_distance: usize = 0;
-    loop {
-        if detect_collision(game, game.y - shadow_distance, &piece) {
-            break;
-        }
-        shadow_distance += 1;
-    }
+                Err(e) => {
+                    eprintln!("infinitris: {}", e);
+                    exit(1);
+                }
+            },
 
-    render_piece(
-        canvas,
-        CELL_SIZE,
-        piece_x as i32,
-        (piece_y + shadow_distance * CELL_SIZE) as i32,
-        &piece,
-        Color::RGBA(63, 63, 63, 15),
-    );
-
-    render_piece(
-        canvas,
-        CELL_SIZE,
-        piece_x as i32,
-        piece_y as i32,
-        &piece,
-        COLORS[game.piece_index],
-    );
-
-    render_piece(
-        canvas,
-        CELL_SIZE / NEXT_ZOOM,
-        (GAME_WIDTH * CELL_SIZE + GAP_WIDTH) as i32,
-        GAP_WIDTH as i32,
-        &PIECES[game.next_piece_index],
-        COLORS[game.next_piece_index],
-    );
+            None => Box::new(bot),
+        };
 
-    canvas.present();
-}
+        // Without a window there is nobody to watch the moves.
+        let delay = if settings.headless {
+            1
+        } else {
+            BOT_INPUT_TICKS
+        };
 
-fn render_piece(
-    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    size: usize,
-    x: i32,
-    y: i32,
-    piece: &Piece,
-    color: Color,
-) {
-    for (j, row) in piece.cells.iter().enumerate() {
-        for (i, cell) in row.iter().enumerate() {
-            if *cell {
-                render_block(
-                    canvas,
-                    size as u32,
-                    x + (i * size) as i32,
-                    y + (j * size) as i32,
-                    color,
-                );
-            }
-        }
-    }
-}
+        Autoplay::new(player, delay)
+    });
 
-fn render_block(
-    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    size: u32,
-    x: i32,
-    y: i32,
-    color: Color,
-) {
-    canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, 127));
-
-    canvas.fill_rect(Rect::new(x, y, size, size)).unwrap();
-
-    canvas.set_draw_color(color);
-
-    canvas
-        .fill_rect(Rect::new(
-            x + CELL_BORDER / 2,
-            y + CELL_BORDER / 2,
-            size - CELL_BORDER as u32,
-            size - CELL_BORDER as u32,
-        ))
-        .unwrap();
-}
+    // The bot's advice for the player, toggled for the rest of the session.
+    let mut hint = Hint::new(bot);
+    let mut hints = false;
 
-pub fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    if let (true, Some(playback)) = (settings.headless, playback.as_mut()) {
+        let mut events = Vec::new();
 
-    let window = video_subsystem
-        .window("sy", WIN_WIDTH as u32, WIN_HEIGHT as u32)
-        .position_centered()
-        .build()
-        .unwrap();
+        while !playback.finished(&play) {
+            playback.tick(&mut play, &mut events);
+            events.clear();
+        }
 
-    let mut canvas = window
-        .into_canvas()
-        .target_texture()
-        .present_vsync()
-        .build()
-        .unwrap();
+        match playback.verdict(&play) {
+            Some(true) => println!("infinitris: replay matches after {} ticks", play.ticks),
 
-    canvas.set_blend_mode(BlendMode::Blend);
-    canvas.set_draw_color(BACKGROUND_COLOR);
-    canvas.clear();
-    canvas.present();
-
-    let mut rng = rand::thread_rng();
-    let mut piece_pool = Vec::new();
-    let mut choose_piece = || {
-        if piece_pool.len() == 0 {
-            for piece in 0..7 {
-                for _ in 0..4 {
-                    piece_pool.push(piece);
-                }
+            Some(false) => {
+                eprintln!("infinitris: replay differs after {} ticks", play.ticks);
+                exit(1);
             }
-        }
 
-        *rng.choose(&piece_pool).unwrap()
-    };
+            None => println!("infinitris: replay has no end to compare with"),
+        }
 
-    let mut game = Game {
-        world: Vec::new(),
-        next_gen: 1,
-        piece_index: choose_piece(),
-        next_piece_index: choose_piece(),
-        orient: 0,
-        y: START_HEIGHT,
-        x: (GAME_WIDTH - 4) / 2,
-    };
+        return;
+    }
 
-    match File::open("infinitris.state") {
-        Ok(mut file) => {
-            let mut header = [0u8; 8];
+    let mut recorder =
+        settings
+            .record
+            .as_ref()
+            .map(|path| match Recorder::create(path, seed, &play) {
+                Ok(recorder) => recorder,
 
-            let size = file.read(&mut header).unwrap();
-            if size < header.len() {
-                panic!("Invalid state (no header)");
-            }
+                Err(e) => {
+                    eprintln!("infinitris: {}", e);
+                    exit(1);
+                }
+            });
 
-            let version = header[0];
-            match version {
-                1 => {
-                    let mut max_gen: u32 = 0;
+    if let (true, Some(mut autoplay)) = (settings.headless, autoplay.take()) {
+        let mut events = Vec::new();
+        let mut failure = None;
 
-                    loop {
-                        let mut bytes = [0u8; GAME_WIDTH * 4];
+        while !play.over() && play.stats.pieces < settings.ai_pieces {
+            let inputs = match autoplay.inputs(&play) {
+                Ok(inputs) => inputs,
 
-                        let size = file.read(&mut bytes).unwrap();
-                        if size == 0 {
-                            break;
-                        }
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            };
 
-                        if size != GAME_WIDTH * 4 {
-                            panic!("Invalid state (read length {})", size);
-                        }
+            if let Some(recorder) = recorder.as_mut() {
+                for input in inputs.iter() {
+                    recorder.record(play.ticks, Action::Input(*input));
+                }
+            }
 
-                        let mut row = [0u32; GAME_WIDTH];
+            play.tick(&inputs, &mut events);
 
-                        for (i, cell) in row.iter_mut().enumerate() {
-                            *cell = ((bytes[i * 4 + 0] as u32) << 24)
-                                | ((bytes[i * 4 + 1] as u32) << 16)
-                                | ((bytes[i * 4 + 2] as u32) << 8)
-                                | (bytes[i * 4 + 3] as u32);
-                            max_gen = u32::max(max_gen, *cell);
-                        }
+            if let Err(e) = autoplay.observe(&play, &events) {
+                failure = Some(e);
+                break;
+            }
 
-                        game.world.push(row);
-                    }
+            events.clear();
+        }
 
-                    game.next_gen = max_gen + 1;
-                }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.finish(play.ticks, &play.game);
+        }
 
-                _ => panic!("Invalid state (version {})", version),
-            }
+        let end = match (&failure, play.game.topped_out, play.completed) {
+            (Some(_), _, _) => "failed",
+            (None, true, _) => "topped out",
+            (None, false, Some(_)) => "finished",
+            (None, false, None) => "stopped",
+        };
+
+        println!(
+            "infinitris: bot {} after {} ticks, {}",
+            end,
+            play.ticks,
+            summary(&play.stats)
+        );
+
+        if let Some(e) = failure {
+            eprintln!("infinitris: {}", e);
+
+            // Exiting skips the drop which tells an external bot to quit.
+            drop(autoplay);
+            exit(1);
         }
+        return;
+    }
 
-        Err(_) => {
-            let mut rng = rand::thread_rng();
+    // Replays and bots don't count for records.
+    let category = goal
+        .filter(|_| playback.is_none() && autoplay.is_none())
+        .map(|goal| Category {
+            goal,
+            width: play.game.width,
+            pieces: play.game.pieces.name.clone(),
+            world: settings.world().describe(),
+        });
+
+    let mut records = match &category {
+        Some(_) => match Records::load() {
+            Ok(records) => records,
+
+            Err(e) => {
+                eprintln!("infinitris: {}", e);
+                exit(1);
+            }
+        },
 
-            for _ in 0..256 {
-                let mut row = [0u32; GAME_WIDTH];
+        None => Records::default(),
+    };
+    let mut best = category.as_ref().and_then(|c| records.best(c));
+    let mut new_best = false;
+    let mut submitted = false;
 
-                loop {
-                    let mut any = false;
-                    let mut all = true;
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
 
-                    for i in 0..row.len() {
-                        if rng.gen_bool(0.5) {
-                            row[i] = 1;
-                            any = true;
-                        } else {
-                            all = false;
-                        }
-                    }
+    let bounds = match video_subsystem.display_usable_bounds(0) {
+        Ok(rect) => (rect.width(), rect.height()),
+        Err(_) => (u32::MAX, u32::MAX),
+    };
+    let (win_width, win_height) = window_size(play.game.width, bounds);
 
-                    if any && !all {
-                        break;
-                    }
-                }
+    let mut window = video_subsystem
+        .window("sy", win_width, win_height)
+        .position_centered()
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .unwrap();
 
-                game.world.push(row);
-            }
+    if settings.fullscreen {
+        window.set_fullscreen(FullscreenType::Desktop).unwrap();
+    }
 
-            game.next_gen = 2;
-        }
-    };
+    let mut canvas = window
+        .into_canvas()
+        .target_texture()
+        .present_vsync()
+        .build()
+        .unwrap();
 
-    game.y = game.world.len() + START_HEIGHT;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(theme.background);
+    canvas.clear();
+    canvas.present();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut interaction = false;
+    let mut style = Style {
+        color_mode: settings.color_mode,
+        age_scheme: settings.age_scheme,
+        legend: !settings.no_legend,
+        theme,
+    };
+
+    if settings.timelapse {
+        run_timelapse(
+            &mut canvas,
+            &mut event_pump,
+            &style,
+            settings.reduced_motion,
+            play.game,
+            journal,
+        );
+        return;
+    }
 
-    let interval = Duration::new(0, 500000000);
-    let mut next_step = Instant::now() + interval;
+    let vsync = canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32 != 0;
+    let tick = Duration::from_secs(1) / TICK_RATE;
+    let mut inputs: Vec<Input> = Vec::new();
+    let mut events: Vec<GameEvent> = Vec::new();
+    let mut anims = Animations::default();
+    let surface = |game: &Game| match game.well {
+        Some(_) => WELL_CAMERA_LEVEL as f64,
+        None => game.world.len() as f64,
+    };
+    let mut camera = Camera::new(surface(&play.game));
+    let mut last_frame = Instant::now();
+    let mut last_y = play.game.y;
+    let mut last_time = Instant::now();
+    let mut lag = Duration::ZERO;
 
     'running: loop {
         let mut pause = false;
 
-        let now = Instant::now();
-        if now >= next_step {
-            if advance_game(&mut canvas, &mut game) {
-                if !interaction {
-                    pause = true;
-                }
-                interaction = false;
-                game.piece_index = game.next_piece_index;
-                game.next_piece_index = choose_piece();
-                game.orient = 0;
-            }
-            next_step = now + interval;
-        }
-
-        render_game(&mut canvas, &game);
-
         for event in event_pump.poll_iter() {
             match event {
                 Event::KeyDown {
@@ -675,8 +637,7 @@ pub fn main() {
                     keycode: Some(Keycode::A),
                     ..
                 } => {
-                    move_piece(&mut game, -1);
-                    interaction = true;
+                    inputs.push(Input::Left);
                 }
 
                 Event::KeyDown {
@@ -687,8 +648,7 @@ pub fn main() {
                     keycode: Some(Keycode::D),
                     ..
                 } => {
-                    move_piece(&mut game, 1);
-                    interaction = true;
+                    inputs.push(Input::Right);
                 }
 
                 Event::KeyDown {
@@ -699,8 +659,7 @@ pub fn main() {
                     keycode: Some(Keycode::W),
                     ..
                 } => {
-                    rotate_piece(&mut game);
-                    interaction = true;
+                    inputs.push(Input::Rotate);
                 }
 
                 Event::KeyDown {
@@ -711,20 +670,21 @@ pub fn main() {
                     keycode: Some(Keycode::S),
                     ..
                 } => {
-                    interaction = true;
-                    next_step = now;
+                    inputs.push(Input::SoftDrop);
                 }
 
                 Event::KeyDown {
                     keycode: Some(Keycode::Space),
                     ..
                 } => {
-                    interaction = true;
-                    drop_piece(&mut canvas, &mut game);
-                    game.piece_index = game.next_piece_index;
-                    game.next_piece_index = choose_piece();
-                    game.orient = 0;
-                    next_step = Instant::now() + interval;
+                    inputs.push(Input::HardDrop);
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::Z),
+                    ..
+                } if undo_depth > 0 => {
+                    inputs.push(Input::Undo);
                 }
 
                 Event::KeyDown {
@@ -734,6 +694,95 @@ pub fn main() {
                     pause = true;
                 }
 
+                // Puzzles can be skipped without solving them.
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::R | Keycode::N)),
+                    ..
+                } if playback.is_none()
+                    && match key {
+                        Keycode::N => pack.is_some(),
+                        _ => play.over(),
+                    } =>
+                {
+                    // A recording covers only the game which ended.
+                    if let Some(mut recorder) = recorder.take() {
+                        recorder.finish(play.ticks, &play.game);
+                    }
+
+                    if let (Keycode::N, Some(pack)) = (key, &pack) {
+                        puzzle_index = (puzzle_index + 1) % pack.puzzles.len();
+                    }
+
+                    let seed = rand::thread_rng().gen();
+                    play = new_play(start.clone().unwrap(), seed, puzzle_index);
+
+                    // A bot which can't start over ends the session.
+                    if let Some(Err(e)) = autoplay.as_mut().map(|autoplay| autoplay.restart()) {
+                        eprintln!("infinitris: {}", e);
+                        break 'running;
+                    }
+                    hint.forget();
+
+                    best = category.as_ref().and_then(|c| records.best(c));
+                    new_best = false;
+                    submitted = false;
+
+                    inputs.clear();
+                    anims = Animations::default();
+                    last_y = play.game.y;
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    ..
+                } => {
+                    toggle_fullscreen(&mut canvas);
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::H),
+                    ..
+                } if playback.is_none() && autoplay.is_none() => {
+                    hints = !hints;
+                }
+
+                // The board is shared as a fumen, printed and copied.
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    ..
+                } => match encode(&play.game) {
+                    Ok(fumen) => {
+                        println!("{}", fumen);
+
+                        if let Err(e) = video_subsystem.clipboard().set_clipboard_text(&fumen) {
+                            eprintln!("infinitris: {}", e);
+                        }
+                    }
+
+                    Err(e) => eprintln!("infinitris: {}", e),
+                },
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::C),
+                    ..
+                } => {
+                    style.color_mode = style.color_mode.next();
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::G),
+                    ..
+                } => {
+                    style.age_scheme = style.age_scheme.next();
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    ..
+                } => {
+                    style.legend = !style.legend;
+                }
+
                 Event::Quit { .. } => {
                     break 'running;
                 }
@@ -742,33 +791,519 @@ pub fn main() {
             }
         }
 
+        let now = Instant::now();
+        play.game.time = unix_time();
+        lag = Duration::min(lag + (now - last_time), tick * MAX_CATCHUP_TICKS);
+        last_time = now;
+
+        while lag >= tick {
+            lag -= tick;
+            last_y = play.game.y;
+
+            let result = match playback.as_mut() {
+                Some(playback) => playback.tick(&mut play, &mut events),
+
+                None => {
+                    // The bot has the controls to itself.
+                    if let Some(autoplay) = autoplay.as_mut() {
+                        inputs = match autoplay.inputs(&play) {
+                            Ok(inputs) => inputs,
+
+                            Err(e) => {
+                                eprintln!("infinitris: {}", e);
+                                break 'running;
+                            }
+                        };
+                    }
+
+                    if let Some(recorder) = recorder.as_mut() {
+                        for input in inputs.iter() {
+                            recorder.record(play.ticks, Action::Input(*input));
+                        }
+                    }
+
+                    let first_event = events.len();
+                    let result = play.tick(&inputs, &mut events);
+
+                    if let Some(autoplay) = autoplay.as_mut() {
+                        if let Err(e) = autoplay.observe(&play, &events[first_event..]) {
+                            eprintln!("infinitris: {}", e);
+                            break 'running;
+                        }
+                    }
+
+                    result
+                }
+            };
+
+            inputs.clear();
+
+            // Nobody is at the keys to walk away during a replay or while the
+            // bot plays.
+            if result.idle && playback.is_none() && autoplay.is_none() {
+                pause = true;
+            }
+
+            if result.locked {
+                last_y = play.game.y;
+            }
+        }
+
+        // A result counts once, and only if the player had no help.
+        if let (Some(ticks), Some(category), false) = (play.completed, &category, submitted) {
+            submitted = true;
+
+            if !play.stats.assisted() {
+                let result = match category.goal {
+                    Goal::Time(_) => play.stats.score,
+                    _ => ticks,
+                };
+
+                new_best = records.submit(category, result, play.game.time);
+            }
+        }
+
+        if let (Some(_), Some(pack), false) = (play.completed, &pack, submitted) {
+            submitted = true;
+
+            if !play.stats.assisted() && autoplay.is_none() {
+                progress.solve(pack, &pack.puzzles[puzzle_index], play.game.time);
+            }
+        }
+
+        let now = Instant::now();
+
+        for event in events.drain(..) {
+            if persistent {
+                match event {
+                    GameEvent::Locked {
+                        piece_index,
+                        orient,
+                        x,
+                        y,
+                        ..
+                    } => {
+                        save_game(&play.game);
+                        append_journal(&JournalEntry {
+                            time: play.game.time,
+                            session: play.game.session,
+                            piece_index: piece_index as u8,
+                            orient,
+                            x: x as u16,
+                            y: y as u32,
+                        });
+                    }
+
+                    GameEvent::Undone => {
+                        save_game(&play.game);
+                        unwind_journal(&play.game);
+                    }
+
+                    _ => {}
+                }
+            }
+
+            match event {
+                // Milestones are news rather than motion.
+                GameEvent::Milestone { left } => {
+                    anims.milestone(left, !settings.reduced_motion, now);
+                }
+
+                event if !settings.reduced_motion => anims.start(event, now),
+                _ => {}
+            }
+        }
+
+        anims.prune(now);
+
+        let surface = surface(&play.game);
+        if settings.reduced_motion {
+            camera.level = surface;
+        } else {
+            camera.update(surface, 1.0, now - last_frame);
+        }
+        last_frame = now;
+
+        // Interpolate the last fall step over the current tick.
+        let fall = if last_y == play.game.y + 1 {
+            1.0 - lag.as_secs_f64() / tick.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        // The drawable size follows window resizes and display changes.
+        let layout = Layout::for_canvas(&canvas, play.game.width);
+
+        // A piece placed with the hint in view makes the game assisted.
+        let hinted = if hints {
+            play.take_hint(&mut hint)
+        } else {
+            None
+        };
+
+        let scene = Scene {
+            game: &play.game,
+            anims: &anims,
+            camera: &camera,
+            fall,
+            now,
+            piece: true,
+            next: !play.randomizer.beyond_queue(),
+            hint: hinted,
+        };
+
+        render_game(&mut canvas, &layout, &style, &scene);
+
+        let layer = play.game.layer;
+        if layer > 0 {
+            let left = usize::min(play.game.garbage_left(), layer);
+            render_progress(
+                &mut canvas,
+                &layout,
+                &style,
+                1.0 - left as f64 / layer as f64,
+            );
+        }
+
+        if let Some(goal) = play.goal {
+            let scale = layout.text_scale();
+            let margin = layout.text_margin();
+            let elapsed = play.completed.unwrap_or(play.ticks);
+
+            let text = match goal {
+                Goal::Lines(lines) => format!(
+                    "SPRINT {}/{} {}",
+                    u32::min(play.stats.lines, lines),
+                    lines,
+                    format_ticks(elapsed)
+                ),
+
+                Goal::Time(seconds) => format!(
+                    "ULTRA {} SCORE {}",
+                    format_ticks((seconds * TICK_RATE).saturating_sub(elapsed)),
+                    play.stats.score
+                ),
+
+                Goal::Depth(0) => format!(
+                    "DIG {} LEFT {}",
+                    play.game.garbage_left(),
+                    format_ticks(elapsed)
+                ),
+
+                Goal::Depth(rows) => format!(
+                    "DIG {} LEFT, GOAL {} {}",
+                    play.game.garbage_left(),
+                    rows,
+                    format_ticks(elapsed)
+                ),
+
+                Goal::Puzzle { objective, moves } => format!(
+                    "PUZZLE {}/{} {} MOVES {}/{}",
+                    puzzle_index + 1,
+                    pack.as_ref().unwrap().puzzles.len(),
+                    objective.describe(),
+                    play.stats.pieces,
+                    moves
+                ),
+            };
+
+            render_text(
+                &mut canvas,
+                layout.left + margin,
+                2 * margin + text_height(scale) as i32,
+                scale,
+                Color::RGB(255, 255, 255),
+                &text,
+            );
+        }
+
+        if play.game.topped_out {
+            let title = match (playback.is_some(), undo_depth > 0) {
+                (true, _) => "GAME OVER",
+                (false, false) => "GAME OVER - R RESTART",
+                (false, true) => "GAME OVER - R RESTART, Z UNDO",
+            };
+
+            render_overlay(&mut canvas, &layout, title, &[summary(&play.stats)]);
+        } else if let (Some(pack), true) = (&pack, play.over()) {
+            let puzzle = &pack.puzzles[puzzle_index];
+            let mut lines = vec![format!(
+                "{} - {}",
+                puzzle.name.to_uppercase(),
+                puzzle.objective.describe()
+            )];
+
+            let title = if play.failed() {
+                if undo_depth > 0 {
+                    "OUT OF MOVES - R RETRY, N SKIP, Z UNDO"
+                } else {
+                    "OUT OF MOVES - R RETRY, N SKIP"
+                }
+            } else {
+                lines.push(if play.stats.assisted() {
+                    "ASSISTED - NOT RECORDED".to_string()
+                } else {
+                    format!("{}/{} SOLVED", progress.count(pack), pack.puzzles.len())
+                });
+
+                "SOLVED - N NEXT, R RETRY"
+            };
+
+            lines.push(summary(&play.stats));
+            render_overlay(&mut canvas, &layout, title, &lines);
+        } else if let (Some(ticks), Some(goal)) = (play.completed, goal) {
+            let mut lines = vec![match goal {
+                Goal::Time(_) => format!("SCORE {}", play.stats.score),
+                _ => format!("TIME {}", format_ticks(ticks)),
+            }];
+
+            if playback.is_none() {
+                let record = if play.stats.assisted() {
+                    Some("ASSISTED - NOT RECORDED".to_string())
+                } else if new_best {
+                    Some("NEW BEST".to_string())
+                } else {
+                    best.map(|best| match goal {
+                        Goal::Time(_) => format!("BEST {}", best),
+                        _ => format!("BEST {}", format_ticks(best)),
+                    })
+                };
+
+                lines.extend(record);
+            }
+
+            for (i, split) in play.splits.iter().enumerate() {
+                let n = i as u32 + 1;
+
+                lines.push(match goal {
+                    Goal::Lines(_) => format!("{} LINES {}", n * SPLIT_LINES, format_ticks(*split)),
+                    Goal::Depth(_) => format!("{} ROWS {}", n * SPLIT_LINES, format_ticks(*split)),
+                    Goal::Time(_) => format!(
+                        "{} SCORE {}",
+                        format_ticks(n * SPLIT_SECONDS * TICK_RATE),
+                        split
+                    ),
+                    Goal::Puzzle { .. } => unreachable!("puzzles have no splits"),
+                });
+            }
+
+            lines.push(summary(&play.stats));
+
+            let title = match (goal, playback.is_some()) {
+                (Goal::Lines(_), true) => "SPRINT COMPLETE",
+                (Goal::Lines(_), false) => "SPRINT COMPLETE - R RESTART",
+                (Goal::Time(_), true) => "TIME UP",
+                (Goal::Time(_), false) => "TIME UP - R RESTART",
+                (Goal::Depth(_), true) => "DIG COMPLETE",
+                (Goal::Depth(_), false) => "DIG COMPLETE - R RESTART",
+                (Goal::Puzzle { .. }, _) => "SOLVED",
+            };
+
+            render_overlay(&mut canvas, &layout, title, &lines);
+        }
+
+        if autoplay.is_some() {
+            render_text(
+                &mut canvas,
+                layout.left + layout.text_margin(),
+                layout.text_margin(),
+                layout.text_scale(),
+                Color::RGB(255, 255, 255),
+                "BOT PLAYING",
+            );
+        } else if undo_depth > 0 && playback.is_none() {
+            let text = match play.stats.undos {
+                0 => "PRACTICE - Z UNDO".to_string(),
+                undos => format!("PRACTICE - Z UNDO, {} USED", undos),
+            };
+
+            render_text(
+                &mut canvas,
+                layout.left + layout.text_margin(),
+                layout.text_margin(),
+                layout.text_scale(),
+                Color::RGB(255, 255, 255),
+                &text,
+            );
+        }
+
+        if let Some(playback) = &playback {
+            let status = match playback.verdict(&play) {
+                Some(true) => " END - MATCHES".to_string(),
+                Some(false) => " END - DIFFERS".to_string(),
+                None if playback.finished(&play) => " END".to_string(),
+                None => String::new(),
+            };
+
+            render_text(
+                &mut canvas,
+                layout.left + layout.text_margin(),
+                layout.text_margin(),
+                layout.text_scale(),
+                Color::RGB(255, 255, 255),
+                &format!("REPLAY {}{}", play.ticks, status),
+            );
+        }
+
+        canvas.present();
+
         if pause {
-            canvas.set_draw_color(Color::RGBA(0, 0, 0, 191));
-            canvas
-                .fill_rect(Rect::new(0, 0, WIN_WIDTH as u32, WIN_HEIGHT as u32))
-                .unwrap();
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(play.ticks, Action::Pause);
+            }
+
+            let mut explore: Option<Explore> = None;
+            let mut menu: Option<ThemeMenu> = None;
 
             'paused: loop {
+                let now = Instant::now();
+                let layout = Layout::for_canvas(&canvas, play.game.width);
+
+                match explore.as_mut() {
+                    Some(explore) => {
+                        explore.update(now - last_frame);
+                        let scene = Scene {
+                            game: &play.game,
+                            anims: &anims,
+                            camera: &explore.camera,
+                            fall: 0.0,
+                            now,
+                            piece: true,
+                            next: !play.randomizer.beyond_queue(),
+                            hint: hinted,
+                        };
+
+                        render_game(&mut canvas, &layout, &style, &scene);
+                        explore.render(&mut canvas, &layout, &play.game);
+                    }
+
+                    None => {
+                        let scene = Scene {
+                            game: &play.game,
+                            anims: &anims,
+                            camera: &camera,
+                            fall: 0.0,
+                            now,
+                            piece: true,
+                            next: !play.randomizer.beyond_queue(),
+                            hint: hinted,
+                        };
+
+                        render_game(&mut canvas, &layout, &style, &scene);
+
+                        canvas.set_draw_color(Color::RGBA(0, 0, 0, 191));
+                        canvas
+                            .fill_rect(Rect::new(0, 0, layout.width as u32, layout.height as u32))
+                            .unwrap();
+
+                        match &menu {
+                            Some(menu) => menu.render(&mut canvas, &layout, &themes),
+
+                            None => {
+                                let margin = layout.text_margin();
+                                let scale = layout.text_scale();
+                                let stats = &play.stats;
+
+                                render_text(
+                                    &mut canvas,
+                                    layout.left + margin,
+                                    margin,
+                                    scale,
+                                    Color::RGB(255, 255, 255),
+                                    "PAUSED - P RESUME, E EXPLORE, T THEME",
+                                );
+
+                                render_text(
+                                    &mut canvas,
+                                    layout.left + margin,
+                                    2 * margin + text_height(scale) as i32,
+                                    scale,
+                                    Color::RGB(191, 191, 191),
+                                    &summary(stats),
+                                );
+                            }
+                        }
+                    }
+                }
+
+                last_frame = now;
                 canvas.present();
 
-                match event_pump.wait_event() {
-                    Event::KeyDown {
+                // Keep redrawing while the explore camera is moving.
+                let event = match &explore {
+                    Some(explore) if !explore.settled() => {
+                        event_pump.wait_event_timeout(1000 / TICK_RATE)
+                    }
+                    _ => Some(event_pump.wait_event()),
+                };
+
+                if let (Some(theme_menu), Some(event @ Event::KeyDown { .. })) =
+                    (menu.as_mut(), &event)
+                {
+                    if !theme_menu.handle_event(&themes, &mut style, event) {
+                        menu = None;
+                    }
+                    continue;
+                }
+
+                match event {
+                    Some(Event::KeyDown {
                         keycode: Some(Keycode::P),
                         ..
-                    } => {
+                    }) => {
                         break 'paused;
                     }
 
-                    Event::Quit { .. } => {
+                    Some(Event::KeyDown {
+                        keycode: Some(Keycode::T),
+                        ..
+                    }) if explore.is_none() => {
+                        menu = Some(ThemeMenu::new(&themes, &style));
+                    }
+
+                    Some(Event::KeyDown {
+                        keycode: Some(Keycode::E),
+                        ..
+                    }) => {
+                        explore = match explore {
+                            Some(_) => None,
+                            None => Some(Explore::new(&camera)),
+                        };
+                    }
+
+                    Some(Event::KeyDown {
+                        keycode: Some(Keycode::F11),
+                        ..
+                    }) => {
+                        toggle_fullscreen(&mut canvas);
+                    }
+
+                    Some(Event::Quit { .. }) => {
                         break 'running;
                     }
 
-                    _ => {}
+                    Some(event) => {
+                        if let Some(explore) = explore.as_mut() {
+                            explore.handle_event(&layout, &play.game, &event);
+                        }
+                    }
+
+                    None => {}
                 }
             }
 
-            interaction = true;
-            next_step = Instant::now() + interval;
+            if playback.is_none() {
+                play.resume();
+            }
+            inputs.clear();
+            last_time = Instant::now();
+            lag = Duration::ZERO;
+        } else if !vsync {
+            // Nothing to do before the next tick, so don't spin.
+            sleep(tick - lag);
         }
     }
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.finish(play.ticks, &play.game);
+    }
 }
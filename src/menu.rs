@@ -0,0 +1,86 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+
+use crate::font::render_text;
+use crate::font::text_height;
+use crate::layout::Layout;
+use crate::render::Canvas;
+use crate::render::Style;
+use crate::theme::Theme;
+
+/// Theme list shown over the pause screen.  The highlighted theme is applied
+/// right away, so the board behind the menu previews it.
+pub struct ThemeMenu {
+    selected: usize,
+}
+
+impl ThemeMenu {
+    pub fn new(themes: &[Theme], style: &Style) -> ThemeMenu {
+        ThemeMenu {
+            selected: themes
+                .iter()
+                .position(|theme| theme.name == style.theme.name)
+                .unwrap_or(0),
+        }
+    }
+
+    /// Returns false when the menu should be closed.
+    pub fn handle_event(&mut self, themes: &[Theme], style: &mut Style, event: &Event) -> bool {
+        if let Event::KeyDown {
+            keycode: Some(keycode),
+            ..
+        } = *event
+        {
+            match keycode {
+                Keycode::Up | Keycode::W => {
+                    self.selected = (self.selected + themes.len() - 1) % themes.len();
+                }
+
+                Keycode::Down | Keycode::S => {
+                    self.selected = (self.selected + 1) % themes.len();
+                }
+
+                Keycode::Return | Keycode::Escape | Keycode::T => return false,
+                _ => return true,
+            }
+
+            style.theme = themes[self.selected].clone();
+        }
+
+        true
+    }
+
+    pub fn render(&self, canvas: &mut Canvas, layout: &Layout, themes: &[Theme]) {
+        let scale = layout.text_scale();
+        let margin = layout.text_margin();
+        let line_height = text_height(scale) as i32 + margin;
+        let x = layout.left + margin;
+
+        render_text(
+            canvas,
+            x,
+            margin,
+            scale,
+            Color::RGB(255, 255, 255),
+            "THEME - ENTER OK",
+        );
+
+        for (n, theme) in themes.iter().enumerate() {
+            let (marker, color) = if n == self.selected {
+                ("> ", Color::RGB(255, 255, 255))
+            } else {
+                ("  ", Color::RGB(127, 127, 127))
+            };
+
+            render_text(
+                canvas,
+                x,
+                margin + (n as i32 + 2) * line_height,
+                scale,
+                color,
+                &format!("{}{}", marker, theme.name),
+            );
+        }
+    }
+}
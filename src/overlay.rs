@@ -0,0 +1,49 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::font::render_text;
+use crate::font::text_height;
+use crate::font::text_width;
+use crate::layout::Layout;
+use crate::render::Canvas;
+
+/// Dims the board and shows a title with lines of detail below it, centered
+/// on the board.
+pub fn render_overlay(canvas: &mut Canvas, layout: &Layout, title: &str, lines: &[String]) {
+    let scale = layout.text_scale();
+    let line_height = layout.text_margin() + text_height(scale) as i32;
+    let center =
+        |text: &str| layout.left + (layout.board_width - text_width(scale, text) as i32) / 2;
+
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 127));
+    canvas
+        .fill_rect(Rect::new(
+            layout.left,
+            0,
+            layout.board_width as u32,
+            layout.height as u32,
+        ))
+        .unwrap();
+
+    let y = layout.height / 3;
+
+    render_text(
+        canvas,
+        center(title),
+        y,
+        scale,
+        Color::RGB(255, 255, 255),
+        title,
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        render_text(
+            canvas,
+            center(line),
+            y + (i + 1) as i32 * line_height,
+            scale,
+            Color::RGB(191, 191, 191),
+            line,
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::fs;
+
+use serde::Deserialize;
+
+/// Largest piece extent in either direction.
+pub const MAX_PIECE_SIZE: usize = 5;
+
+/// Piece types are stored in a byte, with one value reserved for unknown.
+const MAX_PIECES: usize = 255;
+
+static BUILTIN_SETS: [(&str, &str); 3] = [
+    ("tetrominoes", include_str!("pieces/tetrominoes.toml")),
+    ("pentominoes", include_str!("pieces/pentominoes.toml")),
+    ("triominoes", include_str!("pieces/triominoes.toml")),
+];
+
+/// Built-in set made of all the other built-in sets.
+const MIXED_SET: &str = "mixed";
+
+/// Colours for pieces which don't specify one.
+static DEFAULT_COLORS: [[u8; 3]; 6] = [
+    [0, 255, 255],
+    [255, 165, 0],
+    [0, 255, 0],
+    [170, 0, 255],
+    [255, 0, 0],
+    [255, 255, 0],
+];
+
+#[derive(Clone, Copy)]
+pub struct Piece {
+    pub width: usize,
+    pub height: usize,
+    pub cells: [[bool; MAX_PIECE_SIZE]; MAX_PIECE_SIZE],
+}
+
+impl Piece {
+    fn parse(shape: &[String]) -> Result<Piece, String> {
+        let mut cells = [[false; MAX_PIECE_SIZE]; MAX_PIECE_SIZE];
+
+        if shape.len() > MAX_PIECE_SIZE {
+            return Err(format!("more than {} rows", MAX_PIECE_SIZE));
+        }
+
+        for (j, line) in shape.iter().enumerate() {
+            if line.chars().count() > MAX_PIECE_SIZE {
+                return Err(format!("more than {} columns", MAX_PIECE_SIZE));
+            }
+
+            for (i, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells[j][i] = true,
+                    '.' | ' ' => {}
+                    _ => return Err(format!("unexpected character {:?}", c)),
+                }
+            }
+        }
+
+        if cells.iter().flatten().all(|cell| !*cell) {
+            return Err("no cells".to_string());
+        }
+
+        Ok(Piece::normalize(cells))
+    }
+
+    /// Moves the cells to the top-left corner and measures them.
+    fn normalize(mut cells: [[bool; MAX_PIECE_SIZE]; MAX_PIECE_SIZE]) -> Piece {
+        while cells[0].iter().all(|cell| !*cell) {
+            cells.rotate_left(1);
+        }
+
+        while cells.iter().all(|row| !row[0]) {
+            for row in cells.iter_mut() {
+                row.rotate_left(1);
+            }
+        }
+
+        let height = 1
+            + (0..MAX_PIECE_SIZE)
+                .filter(|j| cells[*j].iter().any(|cell| *cell))
+                .max()
+                .unwrap();
+        let width = 1
+            + (0..MAX_PIECE_SIZE)
+                .filter(|i| cells.iter().any(|row| row[*i]))
+                .max()
+                .unwrap();
+
+        Piece {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Rows drawn like in a set file, top row first.
+    pub fn shape(&self) -> Vec<String> {
+        self.cells[..self.height]
+            .iter()
+            .map(|row| {
+                row[..self.width]
+                    .iter()
+                    .map(|cell| if *cell { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Turns the piece a quarter counterclockwise.
+    fn rotate(&self) -> Piece {
+        let mut cells = [[false; MAX_PIECE_SIZE]; MAX_PIECE_SIZE];
+
+        for (j, row) in cells.iter_mut().enumerate() {
+            for (i, cell) in row.iter_mut().enumerate() {
+                *cell = self.cells[i][MAX_PIECE_SIZE - 1 - j];
+            }
+        }
+
+        Piece::normalize(cells)
+    }
+}
+
+#[derive(Deserialize)]
+struct PieceSetFile {
+    name: Option<String>,
+    piece: Vec<PieceFile>,
+}
+
+#[derive(Deserialize)]
+struct PieceFile {
+    name: Option<String>,
+    color: Option<[u8; 3]>,
+    shape: Vec<String>,
+}
+
+pub struct PieceSet {
+    pub name: String,
+    pub pieces: Vec<Piece>,
+    pub names: Vec<String>,
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl PieceSet {
+    /// Loads a built-in set by name, or a set from a TOML file.
+    pub fn load(name: &str) -> Result<PieceSet, String> {
+        if name == MIXED_SET {
+            let mut mixed = PieceSet {
+                name: MIXED_SET.to_string(),
+                pieces: Vec::new(),
+                names: Vec::new(),
+                colors: Vec::new(),
+            };
+
+            for (builtin, text) in BUILTIN_SETS.iter() {
+                let set = PieceSet::parse(text, builtin)?;
+                mixed.pieces.extend(set.pieces);
+                mixed.names.extend(set.names);
+                mixed.colors.extend(set.colors);
+            }
+
+            return Ok(mixed);
+        }
+
+        match BUILTIN_SETS.iter().find(|(builtin, _)| *builtin == name) {
+            Some((builtin, text)) => PieceSet::parse(text, builtin),
+
+            None => {
+                let text = fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+                PieceSet::parse(&text, name).map_err(|e| format!("{}: {}", name, e))
+            }
+        }
+    }
+
+    /// Builds a set from piece names and shapes drawn like in a set file.
+    pub fn from_shapes(name: &str, shapes: Vec<(String, Vec<String>)>) -> Result<PieceSet, String> {
+        let file = PieceSetFile {
+            name: Some(name.to_string()),
+            piece: shapes
+                .into_iter()
+                .map(|(name, shape)| PieceFile {
+                    name: Some(name),
+                    color: None,
+                    shape,
+                })
+                .collect(),
+        };
+
+        PieceSet::from_file(file, name)
+    }
+
+    fn parse(text: &str, default_name: &str) -> Result<PieceSet, String> {
+        let file: PieceSetFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        PieceSet::from_file(file, default_name)
+    }
+
+    fn from_file(file: PieceSetFile, default_name: &str) -> Result<PieceSet, String> {
+        if file.piece.is_empty() {
+            return Err("no pieces".to_string());
+        }
+
+        if file.piece.len() > MAX_PIECES {
+            return Err(format!("more than {} pieces", MAX_PIECES));
+        }
+
+        let mut set = PieceSet {
+            name: file.name.unwrap_or_else(|| default_name.to_string()),
+            pieces: Vec::new(),
+            names: Vec::new(),
+            colors: Vec::new(),
+        };
+
+        for (index, piece) in file.piece.iter().enumerate() {
+            let parsed =
+                Piece::parse(&piece.shape).map_err(|e| format!("piece {}: {}", index + 1, e))?;
+
+            set.pieces.push(parsed);
+            set.names.push(
+                piece
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| (index + 1).to_string()),
+            );
+            set.colors.push(
+                piece
+                    .color
+                    .unwrap_or(DEFAULT_COLORS[index % DEFAULT_COLORS.len()]),
+            );
+        }
+
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Widest extent of any piece in any orientation.
+    pub fn max_size(&self) -> usize {
+        self.pieces
+            .iter()
+            .map(|piece| usize::max(piece.width, piece.height))
+            .max()
+            .unwrap()
+    }
+
+    pub fn effective_piece(&self, index: usize, orient: u8) -> Piece {
+        let mut piece = self.pieces[index];
+
+        for _ in 0..orient {
+            piece = piece.rotate();
+        }
+
+        piece
+    }
+}
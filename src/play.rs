@@ -0,0 +1,509 @@
+use std::collections::VecDeque;
+
+use rand::prng::XorShiftRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::bot::Hint;
+use crate::game::advance_game;
+use crate::game::drop_piece;
+use crate::game::move_piece;
+use crate::game::rotate_piece;
+use crate::game::spawn_piece;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::puzzle::Objective;
+use crate::search::Spot;
+
+pub const TICK_RATE: u32 = 60;
+const GRAVITY_TICKS: u32 = 30;
+
+/// Visible height of the well in classic mode.  Pieces spawn in the buffer
+/// above it.
+pub const WELL_HEIGHT: usize = 20;
+
+/// Lines between sprint splits, and garbage rows between dig splits.
+pub const SPLIT_LINES: u32 = 10;
+/// Seconds between ultra splits.
+pub const SPLIT_SECONDS: u32 = 30;
+
+/// Points for clearing 0 to 5 rows at once.
+const LINE_SCORES: [u32; 6] = [0, 100, 300, 500, 800, 1200];
+/// Points per row of a hard drop.
+const DROP_SCORE: u32 = 2;
+
+/// Rules for a game.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The endless tower, saved between sessions.
+    Infinite,
+    /// A fixed well, and the game is over when it overflows.
+    Classic,
+    /// Clearing a number of lines in a fixed well, against the clock.
+    Sprint,
+    /// Scoring as much as possible in a fixed well before time runs out.
+    Ultra,
+    /// Clearing a pile of garbage, against the clock.
+    Dig,
+    /// Authored boards to solve with given pieces.
+    Puzzle,
+}
+
+impl Mode {
+    pub fn parse(name: &str) -> Option<Mode> {
+        match name {
+            "infinite" => Some(Mode::Infinite),
+            "classic" => Some(Mode::Classic),
+            "sprint" => Some(Mode::Sprint),
+            "ultra" => Some(Mode::Ultra),
+            "dig" => Some(Mode::Dig),
+            "puzzle" => Some(Mode::Puzzle),
+            _ => None,
+        }
+    }
+}
+
+/// What ends a timed game or a puzzle.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Number of lines to clear.
+    Lines(u32),
+    /// Seconds to play.
+    Time(u32),
+    /// Rows of the initial garbage which may remain.
+    Depth(u32),
+    /// An objective to meet within a number of placed pieces.
+    Puzzle { objective: Objective, moves: u32 },
+}
+
+/// Formats a number of ticks as minutes, seconds and hundredths.
+pub fn format_ticks(ticks: u32) -> String {
+    let hundredths = ticks as u64 * 100 / TICK_RATE as u64;
+
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+/// Player actions, applied at the start of a tick.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Left,
+    Right,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    /// Takes back the last placement, if undo is enabled.
+    Undo,
+}
+
+/// Picks pieces from a seeded generator, so that a game can be played again
+/// from its seed.  A fixed queue can be dealt before the random pieces.
+#[derive(Clone)]
+pub struct Randomizer {
+    rng: XorShiftRng,
+    pool: Vec<usize>,
+    queue: VecDeque<usize>,
+    /// There was a fixed queue and it ran out before the last piece dealt.
+    beyond_queue: bool,
+    fixed: bool,
+}
+
+impl Randomizer {
+    pub fn new(seed: u64, piece_count: usize) -> Randomizer {
+        let mut pool = Vec::new();
+
+        for piece in 0..piece_count {
+            for _ in 0..4 {
+                pool.push(piece);
+            }
+        }
+
+        Randomizer {
+            rng: XorShiftRng::seed_from_u64(seed),
+            pool,
+            queue: VecDeque::new(),
+            beyond_queue: false,
+            fixed: false,
+        }
+    }
+
+    /// Deals the given pieces first.
+    pub fn with_queue(mut self, queue: &[usize]) -> Randomizer {
+        self.queue = queue.iter().copied().collect();
+        self.fixed = true;
+        self
+    }
+
+    pub fn next(&mut self) -> usize {
+        match self.queue.pop_front() {
+            Some(piece) => piece,
+
+            None => {
+                self.beyond_queue = self.fixed;
+                *self.rng.choose(&self.pool).unwrap()
+            }
+        }
+    }
+
+    /// True if the last piece dealt wasn't in the fixed queue, so it
+    /// shouldn't be shown as coming up.
+    pub fn beyond_queue(&self) -> bool {
+        self.beyond_queue
+    }
+}
+
+/// Counts for the current session.
+#[derive(Clone, Copy, Default)]
+pub struct Stats {
+    pub pieces: u32,
+    pub lines: u32,
+    pub score: u32,
+    /// Placements taken back.  Games with undos don't count for records.
+    pub undos: u32,
+    /// Pieces placed after being shown a hint, which also keep a game from
+    /// counting.
+    pub hints: u32,
+}
+
+impl Stats {
+    pub fn assisted(&self) -> bool {
+        self.undos > 0 || self.hints > 0
+    }
+}
+
+/// Everything an undo puts back.
+struct Snapshot {
+    game: Game,
+    randomizer: Randomizer,
+    stats: Stats,
+}
+
+/// What happened during a tick.
+#[derive(Default)]
+pub struct Tick {
+    pub locked: bool,
+    /// A piece fell into place without any input since the last one, so the
+    /// player has probably walked away.
+    pub idle: bool,
+}
+
+/// Gravity and input handling on top of the engine, advanced in fixed ticks.
+pub struct Play {
+    pub game: Game,
+    pub randomizer: Randomizer,
+    /// Ticks played so far.
+    pub ticks: u32,
+    pub stats: Stats,
+    gravity: u32,
+    interaction: bool,
+    /// How many placements can be taken back.
+    pub undo_depth: usize,
+    /// States at the spawns of the most recently locked pieces.
+    undo: Vec<Snapshot>,
+    /// State at the spawn of the current piece.
+    spawned: Option<Snapshot>,
+    pub goal: Option<Goal>,
+    /// Tick at which the goal was reached.
+    pub completed: Option<u32>,
+    /// A hint has been shown for the active piece.
+    hinted: bool,
+    /// Ticks at every SPLIT_LINES lines or garbage rows cleared in a sprint
+    /// or a dig, or the score at every SPLIT_SECONDS seconds in an ultra.
+    pub splits: Vec<u32>,
+}
+
+impl Play {
+    pub fn new(game: Game, randomizer: Randomizer, undo_depth: usize) -> Play {
+        Play {
+            game,
+            randomizer,
+            ticks: 0,
+            stats: Stats::default(),
+            gravity: 0,
+            interaction: false,
+            undo_depth,
+            undo: Vec::new(),
+            spawned: None,
+            goal: None,
+            completed: None,
+            hinted: false,
+            splits: Vec::new(),
+        }
+    }
+
+    /// True once nothing but an undo can change the game.
+    pub fn over(&self) -> bool {
+        self.game.topped_out || self.completed.is_some() || self.failed()
+    }
+
+    /// True if a puzzle has run out of moves without being solved.
+    pub fn failed(&self) -> bool {
+        match self.goal {
+            Some(Goal::Puzzle { moves, .. }) => {
+                self.completed.is_none() && self.stats.pieces >= moves
+            }
+            _ => false,
+        }
+    }
+
+    /// Deals the first two pieces of a game.
+    pub fn deal(&mut self) {
+        self.game.piece_index = self.randomizer.next();
+        self.game.next_piece_index = self.randomizer.next();
+        self.game.x = self.game.spawn_x();
+        self.game.y = self.game.spawn_y();
+        self.snapshot();
+    }
+
+    /// Lines or garbage rows cleared towards a sprint or a dig.
+    fn progress(&self) -> Option<u32> {
+        match self.goal {
+            Some(Goal::Lines(_)) => Some(self.stats.lines),
+            Some(Goal::Depth(_)) => {
+                Some(self.game.layer.saturating_sub(self.game.garbage_left()) as u32)
+            }
+            _ => None,
+        }
+    }
+
+    fn snapshot(&mut self) {
+        if self.undo_depth > 0 {
+            self.spawned = Some(Snapshot {
+                game: self.game.clone(),
+                randomizer: self.randomizer.clone(),
+                stats: self.stats,
+            });
+        }
+    }
+
+    /// Goes back to before the last lock.
+    fn undo(&mut self, events: &mut Vec<GameEvent>) {
+        if let Some(snapshot) = self.undo.pop() {
+            let undos = self.stats.undos;
+            let hints = self.stats.hints;
+
+            self.game = snapshot.game;
+            self.randomizer = snapshot.randomizer;
+            self.stats = snapshot.stats;
+            self.stats.undos = undos + 1;
+            self.stats.hints = hints;
+            self.hinted = false;
+            self.gravity = 0;
+            self.snapshot();
+
+            if let Some(progress) = self.progress() {
+                self.splits.truncate((progress / SPLIT_LINES) as usize);
+            }
+
+            events.push(GameEvent::Undone);
+        }
+    }
+
+    pub fn tick(&mut self, inputs: &[Input], events: &mut Vec<GameEvent>) -> Tick {
+        let mut tick = Tick::default();
+
+        // A finished game stays as it ended.
+        if self.completed.is_some() {
+            self.ticks += 1;
+            return tick;
+        }
+
+        for input in inputs.iter() {
+            // A puzzle can be solved in the middle of a tick.
+            if self.completed.is_some() {
+                break;
+            }
+
+            // Only undo can get a game back from topping out or running out
+            // of moves.
+            if self.over() && *input != Input::Undo {
+                continue;
+            }
+
+            self.interaction = true;
+
+            match input {
+                Input::Left => move_piece(&mut self.game, -1),
+                Input::Right => move_piece(&mut self.game, 1),
+                Input::Rotate => rotate_piece(&mut self.game),
+                Input::SoftDrop => self.gravity = GRAVITY_TICKS,
+                Input::Undo => self.undo(events),
+
+                Input::HardDrop => {
+                    let first_event = events.len();
+                    drop_piece(&mut self.game, events);
+                    self.locked(&events[first_event..]);
+                    tick.locked = true;
+                    self.gravity = 0;
+                }
+            }
+        }
+
+        if !tick.locked && !self.over() {
+            self.gravity += 1;
+            if self.gravity >= GRAVITY_TICKS {
+                self.gravity = 0;
+
+                let first_event = events.len();
+                if advance_game(&mut self.game, events) {
+                    tick.idle = !self.interaction;
+                    tick.locked = true;
+                    self.locked(&events[first_event..]);
+                }
+            }
+        }
+
+        self.ticks += 1;
+        self.check_goal();
+        tick
+    }
+
+    fn check_goal(&mut self) {
+        if self.game.topped_out {
+            return;
+        }
+
+        if let Some(progress) = self.progress() {
+            while self.splits.len() < (progress / SPLIT_LINES) as usize {
+                self.splits.push(self.ticks);
+            }
+        }
+
+        let reached = match self.goal {
+            Some(Goal::Lines(lines)) => self.stats.lines >= lines,
+
+            Some(Goal::Time(seconds)) => {
+                if self.ticks.is_multiple_of(SPLIT_SECONDS * TICK_RATE) {
+                    self.splits.push(self.stats.score);
+                }
+
+                self.ticks >= seconds * TICK_RATE
+            }
+
+            Some(Goal::Depth(rows)) => self.game.garbage_left() <= rows as usize,
+
+            // Puzzles are checked as pieces lock.
+            Some(Goal::Puzzle { .. }) | None => false,
+        };
+
+        if reached {
+            self.completed = Some(self.ticks);
+        }
+    }
+
+    /// Counts the piece which was just locked and brings in the next one.
+    fn locked(&mut self, events: &[GameEvent]) {
+        self.stats.pieces += 1;
+
+        if self.hinted {
+            self.stats.hints += 1;
+            self.hinted = false;
+        }
+
+        for event in events.iter() {
+            match event {
+                GameEvent::Dropped { from_y, to_y, .. } => {
+                    self.stats.score += (from_y - to_y) as u32 * DROP_SCORE;
+                }
+
+                GameEvent::Cleared { rows } => {
+                    self.stats.lines += rows.len() as u32;
+                    self.stats.score += LINE_SCORES[usize::min(rows.len(), LINE_SCORES.len() - 1)];
+                }
+
+                _ => {}
+            }
+        }
+
+        if let Some(Goal::Puzzle { objective, .. }) = self.goal {
+            let solved = match objective {
+                Objective::Lines(lines) => self.stats.lines >= lines,
+                Objective::PerfectClear => self.game.world.is_empty(),
+                Objective::Depth(rows) => self.game.garbage_left() <= rows as usize,
+            };
+
+            // The tick counter goes up once the tick is done.
+            if solved {
+                self.completed = Some(self.ticks + 1);
+            }
+        }
+
+        if let Some(snapshot) = self.spawned.take() {
+            if self.undo.len() == self.undo_depth {
+                self.undo.remove(0);
+            }
+            self.undo.push(snapshot);
+        }
+
+        self.interaction = false;
+        spawn_piece(&mut self.game, self.randomizer.next());
+        self.snapshot();
+    }
+
+    /// Where the hint would put the active piece.  Placing a piece after its
+    /// hint has been shown makes the game assisted.
+    pub fn take_hint(&mut self, hint: &mut Hint) -> Option<Spot> {
+        if self.over() {
+            return None;
+        }
+
+        let spot = hint.spot(self);
+        self.hinted |= spot.is_some();
+        spot
+    }
+
+    /// Picks up again after a pause.
+    pub fn resume(&mut self) {
+        self.interaction = true;
+        self.gravity = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::garbage_row;
+    use crate::game::test_game;
+    use crate::garbage::GARBAGE_GEN;
+
+    #[test]
+    fn undo_restores_the_spawn() {
+        let game = test_game(10, vec![garbage_row("#########.")]);
+        let piece_count = game.pieces.len();
+        let mut play = Play::new(game, Randomizer::new(1, piece_count), 2);
+        play.deal();
+
+        let mut events = Vec::new();
+        let mut states = Vec::new();
+
+        for _ in 0..3 {
+            let game = &play.game;
+            states.push((game.world.clone(), game.piece_index, game.next_piece_index));
+            play.tick(&[Input::Left, Input::HardDrop], &mut events);
+        }
+
+        // Only as many placements as the depth can be taken back.
+        events.clear();
+        play.tick(&[Input::Undo, Input::Undo, Input::Undo], &mut events);
+        let undone = events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::Undone))
+            .count();
+        assert_eq!(undone, 2);
+
+        let game = &play.game;
+        assert!((game.world.clone(), game.piece_index, game.next_piece_index) == states[1]);
+        assert_eq!(game.next_gen, GARBAGE_GEN + 2);
+        assert_eq!(play.stats.pieces, 1);
+        assert_eq!(play.stats.undos, 2);
+
+        // The same pieces come again.
+        play.tick(&[Input::Left, Input::HardDrop], &mut events);
+        let game = &play.game;
+        assert!((game.world.clone(), game.piece_index, game.next_piece_index) == states[2]);
+    }
+}
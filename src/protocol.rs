@@ -0,0 +1,1122 @@
+use std::collections::VecDeque;
+use std::fs::metadata;
+use std::fs::remove_file;
+use std::io;
+use std::io::stdin;
+use std::io::stdout;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bot::view_floor;
+use crate::bot::visible_placements;
+use crate::bot::Bot;
+use crate::bot::Player;
+use crate::game::drop_piece;
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::game::Generation;
+use crate::game::UNKNOWN_PIECE;
+use crate::garbage::GARBAGE_GEN;
+use crate::pieces::PieceSet;
+use crate::play::Input;
+use crate::play::Play;
+use crate::search::follow;
+use crate::search::Spot;
+
+// Bots are spoken to in JSON messages, one per line, after the Tetris Bot
+// Protocol.  The bot introduces itself with "info", and is told the rules,
+// which it answers with "ready".  Then it's told about a game with "start",
+// asked for moves with "suggest" and told what was played with "play" and
+// "new_piece", until the game ends with "stop".
+//
+// The board is a list of rows from the bottom up, and a cell is null, the
+// name of the piece it came from, or "G" for garbage.  Only the rows the
+// built-in bot looks at are sent, and rows are counted from the lowest of
+// them.  When that changes, the game is stopped and started again.  A location is the
+// bottom-left corner of the piece, turned counterclockwise from its spawn
+// orientation: north, west, south and then east.  Pieces are turned in
+// place, without kicks.
+
+/// Orientation names by the number of counterclockwise turns.
+const ORIENTATIONS: [&str; 4] = ["north", "west", "south", "east"];
+
+/// Cells which didn't come from a known piece.
+const GARBAGE: &str = "G";
+
+/// How long a bot has to answer before it's given up on.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a bot has to exit when told to quit, before it's killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where a bot is found.
+pub enum Link {
+    /// This process's standard input and output.
+    Stdio,
+    /// Standard input and output of a shell command.
+    Command(String),
+    /// A Unix socket.
+    Socket(String),
+}
+
+/// Messages from the game to a bot.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum GameMessage {
+    /// How the game is played, sent once the bot has introduced itself.
+    Rules {
+        width: usize,
+        /// Height of the well, or null for an endless tower.
+        well: Option<usize>,
+        pieces: Vec<PieceRule>,
+    },
+
+    /// A game to play from now on.  The queue starts with the active piece.
+    /// There is no hold, combo or back-to-back in this game.
+    Start {
+        board: Vec<Vec<Option<String>>>,
+        queue: Vec<String>,
+        hold: Option<String>,
+        combo: u32,
+        back_to_back: bool,
+    },
+
+    /// Asks for moves for the first piece in the queue.
+    Suggest,
+
+    /// The first piece in the queue was played.
+    Play {
+        #[serde(rename = "move")]
+        played: Move,
+    },
+
+    /// A piece was added to the end of the queue.
+    NewPiece { piece: String },
+
+    /// The game is over.
+    Stop,
+
+    /// The bot should exit.
+    Quit,
+
+    #[serde(other)]
+    Unknown,
+}
+
+/// Messages from a bot to the game.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+
+    Ready,
+
+    Error {
+        reason: String,
+    },
+
+    /// Moves for the first piece in the queue, best first.
+    Suggestion {
+        moves: Vec<Move>,
+    },
+
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PieceRule {
+    name: String,
+    /// Rows drawn like in a piece set file, top row first.
+    shape: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Move {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+
+    /// Inputs to give instead of a location: "left", "right", "rotate",
+    /// "soft_drop" and "hard_drop".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inputs: Option<Vec<String>>,
+
+    /// "full" for a T-spin, otherwise "none".
+    #[serde(default = "no_spin")]
+    spin: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Location {
+    #[serde(rename = "type")]
+    piece: String,
+    orientation: String,
+    x: usize,
+    y: usize,
+}
+
+fn no_spin() -> String {
+    "none".to_string()
+}
+
+fn spin_name(spin: bool) -> String {
+    if spin {
+        "full".to_string()
+    } else {
+        no_spin()
+    }
+}
+
+fn parse_input(name: &str) -> Option<Input> {
+    match name {
+        "left" => Some(Input::Left),
+        "right" => Some(Input::Right),
+        "rotate" => Some(Input::Rotate),
+        "soft_drop" => Some(Input::SoftDrop),
+        "hard_drop" => Some(Input::HardDrop),
+        _ => None,
+    }
+}
+
+/// Reads what a thread reads from a bot, failing if nothing comes in time.
+struct Deadline {
+    chunks: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    read: usize,
+}
+
+impl Deadline {
+    fn new(mut reader: impl Read + Send + 'static) -> Deadline {
+        let (sender, chunks) = channel();
+
+        // Ends when the bot hangs up, or when nobody is listening any more.
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+
+            while let Ok(count) = reader.read(&mut buffer) {
+                if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Deadline {
+            chunks,
+            chunk: Vec::new(),
+            read: 0,
+        }
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.chunk.len() {
+            self.chunk = match self.chunks.recv_timeout(REPLY_TIMEOUT) {
+                Ok(chunk) => chunk,
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        format!("no answer within {} seconds", REPLY_TIMEOUT.as_secs()),
+                    ));
+                }
+            };
+            self.read = 0;
+        }
+
+        let count = usize::min(buffer.len(), self.chunk.len() - self.read);
+        buffer[..count].copy_from_slice(&self.chunk[self.read..self.read + count]);
+        self.read += count;
+        Ok(count)
+    }
+}
+
+/// One message per line each way.
+struct Connection {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+}
+
+impl Connection {
+    fn socket(stream: UnixStream) -> Result<Connection, String> {
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+
+        Ok(Connection {
+            reader: Box::new(BufReader::new(reader)),
+            writer: Box::new(stream),
+        })
+    }
+
+    /// A connection to a bot, which has to answer in time.
+    fn to_bot(reader: impl Read + Send + 'static, writer: impl Write + 'static) -> Connection {
+        Connection {
+            reader: Box::new(BufReader::new(Deadline::new(reader))),
+            writer: Box::new(writer),
+        }
+    }
+
+    fn send(&mut self, message: &impl Serialize) -> Result<(), String> {
+        let line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        writeln!(self.writer, "{}", line).map_err(|e| e.to_string())?;
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// The next message, or None once the other end has hung up.
+    fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<T>, String> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| e.to_string())?
+                == 0
+            {
+                return Ok(None);
+            }
+
+            if !line.trim().is_empty() {
+                return serde_json::from_str(&line).map_err(|e| format!("bad message: {}", e));
+            }
+        }
+    }
+}
+
+fn piece_name(game: &Game, index: usize) -> String {
+    game.pieces.names[index].clone()
+}
+
+fn cell_name(game: &Game, cell: &Cell) -> Option<String> {
+    match cell.piece as usize {
+        _ if cell.is_empty() => None,
+        index if index < game.pieces.len() => Some(piece_name(game, index)),
+        _ => Some(GARBAGE.to_string()),
+    }
+}
+
+/// A bot in another process, which plays through the messages.
+pub struct External {
+    connection: Connection,
+    child: Option<Child>,
+    name: String,
+    started: bool,
+    /// Row of the game which is the bottom of the bot's board.
+    floor: usize,
+    /// Where the bot wants the active piece, and the pieces placed when it
+    /// said so.
+    target: Option<(u32, Spot)>,
+}
+
+impl External {
+    /// Reaches the bot and tells it the rules of the game.
+    pub fn connect(link: &Link, game: &Game) -> Result<External, String> {
+        let (connection, child) = match link {
+            Link::Stdio => return Err("the game's own input isn't a bot".to_string()),
+
+            Link::Command(command) => {
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| format!("{}: {}", command, e))?;
+
+                let connection =
+                    Connection::to_bot(child.stdout.take().unwrap(), child.stdin.take().unwrap());
+
+                (connection, Some(child))
+            }
+
+            Link::Socket(path) => {
+                let error = |e: io::Error| format!("{}: {}", path, e);
+                let stream = UnixStream::connect(path).map_err(error)?;
+                let reader = stream.try_clone().map_err(error)?;
+                (Connection::to_bot(reader, stream), None)
+            }
+        };
+
+        External::greet(connection, child, game)
+    }
+
+    /// Waits for the bot to introduce itself, and tells it the rules.
+    fn greet(
+        connection: Connection,
+        child: Option<Child>,
+        game: &Game,
+    ) -> Result<External, String> {
+        let mut external = External {
+            connection,
+            child,
+            name: "bot".to_string(),
+            started: false,
+            floor: 0,
+            target: None,
+        };
+
+        external.name = match external.connection.receive()? {
+            Some(BotMessage::Info { name, version, .. }) => format!("{} {}", name, version),
+            Some(_) => return Err("bot didn't introduce itself".to_string()),
+            None => return Err("bot hung up".to_string()),
+        };
+
+        let pieces = (0..game.pieces.len())
+            .map(|index| PieceRule {
+                name: piece_name(game, index),
+                shape: game.pieces.pieces[index].shape(),
+            })
+            .collect();
+
+        external.connection.send(&GameMessage::Rules {
+            width: game.width,
+            well: game.well,
+            pieces,
+        })?;
+
+        loop {
+            match external.connection.receive()? {
+                Some(BotMessage::Ready) => return Ok(external),
+                Some(BotMessage::Error { reason }) => return Err(external.error(&reason)),
+                Some(_) => {}
+                None => return Err(external.error("hung up")),
+            }
+        }
+    }
+
+    fn error(&self, reason: &str) -> String {
+        format!("{}: {}", self.name, reason)
+    }
+
+    fn start(&mut self, play: &Play) -> Result<(), String> {
+        let game = &play.game;
+        let mut queue = vec![piece_name(game, game.piece_index)];
+
+        if !play.randomizer.beyond_queue() {
+            queue.push(piece_name(game, game.next_piece_index));
+        }
+
+        self.floor = view_floor(game);
+        self.connection.send(&GameMessage::Start {
+            board: game.world[self.floor..]
+                .iter()
+                .map(|row| row.iter().map(|cell| cell_name(game, cell)).collect())
+                .collect(),
+            queue,
+            hold: None,
+            combo: 0,
+            back_to_back: false,
+        })
+    }
+
+    fn suggest(&mut self) -> Result<Vec<Move>, String> {
+        self.connection.send(&GameMessage::Suggest)?;
+
+        loop {
+            match self.connection.receive()? {
+                Some(BotMessage::Suggestion { moves }) => return Ok(moves),
+                Some(BotMessage::Error { reason }) => return Err(reason),
+                Some(_) => {}
+                None => return Err("hung up".to_string()),
+            }
+        }
+    }
+
+    /// The path for the first of the moves which can be played.  Placements
+    /// are looked for in the same rows as the built-in bot looks at.
+    fn choose(&mut self, play: &Play, moves: &[Move]) -> Option<Vec<(Spot, Input)>> {
+        let game = &play.game;
+        let found = visible_placements(game);
+
+        for played in moves.iter() {
+            if let Some(names) = &played.inputs {
+                let inputs: Option<Vec<Input>> =
+                    names.iter().map(|name| parse_input(name)).collect();
+
+                if let Some(inputs) = inputs {
+                    self.target = None;
+                    return Some(follow(game, &inputs));
+                }
+            }
+
+            let Some(location) = &played.location else {
+                continue;
+            };
+
+            let orient = ORIENTATIONS
+                .iter()
+                .position(|name| *name == location.orientation);
+
+            if let (true, Some(orient)) =
+                (location.piece == piece_name(game, game.piece_index), orient)
+            {
+                let spot = Spot {
+                    x: location.x,
+                    y: location.y + self.floor,
+                    orient: orient as u8,
+                };
+                let spin = played.spin == spin_name(true);
+
+                // Prefer the way which spins as the bot said, if there are two.
+                let placement = found
+                    .iter()
+                    .find(|p| p.spot == spot && p.spin == spin)
+                    .or_else(|| found.iter().find(|p| p.spot == spot));
+
+                if let Some(placement) = placement {
+                    self.target = Some((play.stats.pieces, spot));
+                    return Some(placement.path.clone());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Player for External {
+    fn plan(&mut self, play: &Play) -> Result<Vec<(Spot, Input)>, String> {
+        if self.started && view_floor(&play.game) != self.floor {
+            self.restart()?;
+        }
+
+        if !self.started {
+            self.start(play).map_err(|e| self.error(&e))?;
+            self.started = true;
+        }
+
+        // A piece which gravity moved off the path is taken to the same spot
+        // without asking again.
+        if let Some((pieces, spot)) = self.target {
+            if pieces == play.stats.pieces {
+                let again = visible_placements(&play.game)
+                    .into_iter()
+                    .find(|placement| placement.spot == spot);
+
+                if let Some(placement) = again {
+                    return Ok(placement.path);
+                }
+            }
+        }
+
+        let moves = self.suggest().map_err(|e| self.error(&e))?;
+
+        self.choose(play, &moves)
+            .ok_or_else(|| self.error("none of the suggested moves can be played"))
+    }
+
+    fn locked(&mut self, play: &Play, event: &GameEvent) -> Result<(), String> {
+        let GameEvent::Locked {
+            piece_index,
+            orient,
+            x,
+            y,
+            spin,
+            ..
+        } = event
+        else {
+            return Ok(());
+        };
+
+        if !self.started {
+            return Ok(());
+        }
+
+        // A lock the bot can't be told of in its rows is caught up on with a
+        // new board instead.
+        let game = &play.game;
+        if view_floor(game) != self.floor || *y < self.floor {
+            return self.restart();
+        }
+
+        let mut messages = vec![GameMessage::Play {
+            played: Move {
+                location: Some(Location {
+                    piece: piece_name(game, *piece_index),
+                    orientation: ORIENTATIONS[*orient as usize].to_string(),
+                    x: *x,
+                    y: *y - self.floor,
+                }),
+                inputs: None,
+                spin: spin_name(*spin),
+            },
+        }];
+
+        if !play.randomizer.beyond_queue() {
+            messages.push(GameMessage::NewPiece {
+                piece: piece_name(game, game.next_piece_index),
+            });
+        }
+
+        for message in messages.iter() {
+            self.connection.send(message).map_err(|e| self.error(&e))?;
+        }
+
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<(), String> {
+        if self.started {
+            self.connection
+                .send(&GameMessage::Stop)
+                .map_err(|e| self.error(&e))?;
+        }
+
+        self.started = false;
+        self.target = None;
+        Ok(())
+    }
+}
+
+impl Drop for External {
+    fn drop(&mut self) {
+        // The bot may be gone already.
+        let _ = self.connection.send(&GameMessage::Quit);
+
+        // A bot which doesn't quit in time is killed.
+        if let Some(child) = self.child.as_mut() {
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+
+            while let Ok(None) = child.try_wait() {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+/// What the reference bot knows about the game being played.
+struct Session {
+    pieces: Option<Rc<PieceSet>>,
+    width: usize,
+    well: Option<usize>,
+    game: Option<Game>,
+    queue: VecDeque<usize>,
+}
+
+impl Session {
+    fn index(&self, name: &str) -> Result<usize, String> {
+        self.pieces
+            .as_ref()
+            .and_then(|pieces| pieces.names.iter().position(|n| n == name))
+            .ok_or_else(|| format!("unknown piece {:?}", name))
+    }
+
+    fn start(&mut self, board: &[Vec<Option<String>>], queue: &[String]) -> Result<(), String> {
+        let pieces = self.pieces.clone().ok_or("no rules")?;
+        let mut world = Vec::new();
+
+        for row in board.iter() {
+            if row.len() != self.width {
+                return Err(format!(
+                    "a row of {} cells on a board {} wide",
+                    row.len(),
+                    self.width
+                ));
+            }
+
+            let row = row
+                .iter()
+                .map(|cell| match cell {
+                    None => Ok(Cell::default()),
+
+                    Some(name) => Ok(Cell {
+                        gen: GARBAGE_GEN,
+                        piece: match name.as_str() {
+                            GARBAGE => UNKNOWN_PIECE,
+                            name => self.index(name)? as u8,
+                        },
+                    }),
+                })
+                .collect::<Result<Vec<Cell>, String>>()?;
+
+            world.push(row);
+        }
+
+        while world
+            .last()
+            .is_some_and(|row| row.iter().all(|cell| cell.is_empty()))
+        {
+            world.pop();
+        }
+
+        self.queue = queue
+            .iter()
+            .map(|name| self.index(name))
+            .collect::<Result<VecDeque<usize>, String>>()?;
+
+        self.game = Some(Game {
+            pieces,
+            width: self.width,
+            world,
+            next_gen: GARBAGE_GEN + 1,
+            history: vec![Generation::default(); GARBAGE_GEN as usize + 1],
+            layer: 0,
+            session: 0,
+            time: 0,
+            piece_index: 0,
+            next_piece_index: 0,
+            orient: 0,
+            y: 0,
+            x: 0,
+            rotated: false,
+            well: self.well,
+            topped_out: false,
+        });
+
+        Ok(())
+    }
+
+    fn suggest(&mut self, bot: &Bot) -> Result<Vec<Move>, String> {
+        let game = self.game.as_mut().ok_or("no game")?;
+        let piece_index = *self.queue.front().ok_or("empty queue")?;
+
+        game.piece_index = piece_index;
+        game.next_piece_index = self.queue.get(1).copied().unwrap_or(piece_index);
+        game.orient = 0;
+        game.rotated = false;
+        game.x = game.spawn_x();
+        game.y = game.spawn_y();
+
+        // The next piece can only be looked at if it's known.
+        let bot = Bot {
+            lookahead: bot.lookahead && self.queue.len() > 1,
+            ..*bot
+        };
+
+        Ok(bot
+            .choose(game)
+            .map(|placement| Move {
+                location: Some(Location {
+                    piece: piece_name(game, piece_index),
+                    orientation: ORIENTATIONS[placement.spot.orient as usize].to_string(),
+                    x: placement.spot.x,
+                    y: placement.spot.y,
+                }),
+                inputs: None,
+                spin: spin_name(placement.spin),
+            })
+            .into_iter()
+            .collect())
+    }
+
+    fn play(&mut self, played: &Move) -> Result<(), String> {
+        let location = played
+            .location
+            .as_ref()
+            .ok_or("a move without a location")?;
+        let piece_index = self.index(&location.piece)?;
+        let game = self.game.as_mut().ok_or("no game")?;
+
+        if self.queue.pop_front() != Some(piece_index) {
+            return Err(format!("{} wasn't first in the queue", location.piece));
+        }
+
+        let orient = ORIENTATIONS
+            .iter()
+            .position(|name| *name == location.orientation)
+            .ok_or_else(|| format!("unknown orientation {:?}", location.orientation))?;
+
+        game.piece_index = piece_index;
+        game.orient = orient as u8;
+        game.x = location.x;
+        game.y = location.y;
+
+        if game.x + game.effective_piece().width > game.width {
+            return Err("a move off the board".to_string());
+        }
+
+        drop_piece(game, &mut Vec::new());
+        Ok(())
+    }
+
+    /// Takes in a message, and answers it if it needs an answer.
+    fn handle(&mut self, message: GameMessage, bot: &Bot) -> Result<Option<BotMessage>, String> {
+        let reply = match message {
+            GameMessage::Rules {
+                width,
+                well,
+                pieces,
+            } => {
+                let shapes = pieces
+                    .into_iter()
+                    .map(|rule| (rule.name, rule.shape))
+                    .collect();
+                let pieces = PieceSet::from_shapes("rules", shapes)?;
+
+                if pieces.max_size() > width {
+                    return Err(format!("pieces don't fit on a board {} wide", width));
+                }
+
+                self.pieces = Some(Rc::new(pieces));
+                self.width = width;
+                self.well = well;
+                Some(BotMessage::Ready)
+            }
+
+            GameMessage::Start { board, queue, .. } => {
+                self.start(&board, &queue)?;
+                None
+            }
+
+            GameMessage::Suggest => Some(BotMessage::Suggestion {
+                moves: self.suggest(bot)?,
+            }),
+
+            GameMessage::Play { played } => {
+                self.play(&played)?;
+                None
+            }
+
+            GameMessage::NewPiece { piece } => {
+                let index = self.index(&piece)?;
+                self.queue.push_back(index);
+                None
+            }
+
+            GameMessage::Stop => {
+                self.game = None;
+                self.queue.clear();
+                None
+            }
+
+            GameMessage::Quit | GameMessage::Unknown => None,
+        };
+
+        Ok(reply)
+    }
+}
+
+/// Speaks for the built-in bot over a connection, until the game quits or
+/// hangs up.
+fn serve_connection(connection: &mut Connection, bot: &Bot) -> Result<(), String> {
+    connection.send(&BotMessage::Info {
+        name: "infinitris".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        author: env!("CARGO_PKG_AUTHORS").to_string(),
+        features: Vec::new(),
+    })?;
+
+    let mut session = Session {
+        pieces: None,
+        width: 0,
+        well: None,
+        game: None,
+        queue: VecDeque::new(),
+    };
+
+    while let Some(message) = connection.receive()? {
+        if let GameMessage::Quit = message {
+            break;
+        }
+
+        let reply = session
+            .handle(message, bot)
+            .unwrap_or_else(|reason| Some(BotMessage::Error { reason }));
+
+        if let Some(reply) = reply {
+            connection.send(&reply)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a socket left behind by an earlier bot, so that it can be bound
+/// again.  A socket which is still being served, or a file which isn't a
+/// socket, is left alone.
+fn remove_stale_socket(path: &str) -> Result<(), String> {
+    let is_socket = metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+
+    if is_socket {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{}: another bot is already being served", path));
+        }
+
+        remove_file(path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    Ok(())
+}
+
+/// Runs the built-in bot as a reference for the protocol, on standard input
+/// and output or for one game at a time on a Unix socket.  A bot on a socket
+/// waits for the next game when one quits.
+pub fn serve(link: &Link, bot: &Bot) -> Result<(), String> {
+    match link {
+        Link::Stdio => {
+            let mut connection = Connection {
+                reader: Box::new(stdin().lock()),
+                writer: Box::new(stdout()),
+            };
+
+            serve_connection(&mut connection, bot)
+        }
+
+        Link::Command(_) => Err("the bot is served, not run".to_string()),
+
+        Link::Socket(path) => {
+            remove_stale_socket(path)?;
+            let listener = UnixListener::bind(path).map_err(|e| format!("{}: {}", path, e))?;
+
+            for stream in listener.incoming() {
+                let stream = stream.map_err(|e| format!("{}: {}", path, e))?;
+                let mut connection = Connection::socket(stream)?;
+
+                // A game which goes away doesn't take the bot with it.
+                if let Err(e) = serve_connection(&mut connection, bot) {
+                    eprintln!("infinitris: {}", e);
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::bot::Weights;
+    use crate::game::garbage_row;
+    use crate::game::test_game;
+    use crate::play::Randomizer;
+
+    /// Checks that a message is written as the JSON, and read back from it.
+    fn pinned<T: Serialize + DeserializeOwned>(message: T, expected: Value) {
+        assert_eq!(serde_json::to_value(&message).unwrap(), expected);
+        let read: T = serde_json::from_value(expected.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), expected);
+    }
+
+    #[test]
+    fn game_messages() {
+        pinned(
+            GameMessage::Rules {
+                width: 10,
+                well: None,
+                pieces: vec![PieceRule {
+                    name: "O".to_string(),
+                    shape: vec!["##".to_string(), "##".to_string()],
+                }],
+            },
+            json!({
+                "type": "rules",
+                "width": 10,
+                "well": null,
+                "pieces": [{"name": "O", "shape": ["##", "##"]}],
+            }),
+        );
+
+        pinned(
+            GameMessage::Start {
+                board: vec![vec![None, Some("G".to_string()), Some("T".to_string())]],
+                queue: vec!["I".to_string(), "O".to_string()],
+                hold: None,
+                combo: 0,
+                back_to_back: false,
+            },
+            json!({
+                "type": "start",
+                "board": [[null, "G", "T"]],
+                "queue": ["I", "O"],
+                "hold": null,
+                "combo": 0,
+                "back_to_back": false,
+            }),
+        );
+
+        pinned(GameMessage::Suggest, json!({"type": "suggest"}));
+
+        pinned(
+            GameMessage::Play {
+                played: Move {
+                    location: Some(Location {
+                        piece: "T".to_string(),
+                        orientation: "south".to_string(),
+                        x: 3,
+                        y: 1,
+                    }),
+                    inputs: None,
+                    spin: spin_name(true),
+                },
+            },
+            json!({
+                "type": "play",
+                "move": {
+                    "location": {"type": "T", "orientation": "south", "x": 3, "y": 1},
+                    "spin": "full",
+                },
+            }),
+        );
+
+        pinned(
+            GameMessage::NewPiece {
+                piece: "S".to_string(),
+            },
+            json!({"type": "new_piece", "piece": "S"}),
+        );
+
+        pinned(GameMessage::Stop, json!({"type": "stop"}));
+        pinned(GameMessage::Quit, json!({"type": "quit"}));
+
+        let unknown = serde_json::from_value(json!({"type": "pause", "for": 3})).unwrap();
+        assert!(matches!(unknown, GameMessage::Unknown));
+    }
+
+    #[test]
+    fn bot_messages() {
+        pinned(
+            BotMessage::Info {
+                name: "bot".to_string(),
+                version: "1.0".to_string(),
+                author: "someone".to_string(),
+                features: Vec::new(),
+            },
+            json!({
+                "type": "info",
+                "name": "bot",
+                "version": "1.0",
+                "author": "someone",
+                "features": [],
+            }),
+        );
+
+        pinned(BotMessage::Ready, json!({"type": "ready"}));
+
+        pinned(
+            BotMessage::Error {
+                reason: "no".to_string(),
+            },
+            json!({"type": "error", "reason": "no"}),
+        );
+
+        pinned(
+            BotMessage::Suggestion {
+                moves: vec![Move {
+                    location: None,
+                    inputs: Some(vec!["left".to_string(), "hard_drop".to_string()]),
+                    spin: no_spin(),
+                }],
+            },
+            json!({
+                "type": "suggestion",
+                "moves": [{"inputs": ["left", "hard_drop"], "spin": "none"}],
+            }),
+        );
+
+        // A move needn't say it doesn't spin.
+        let suggestion = json!({"type": "suggestion", "moves": [{"inputs": []}]});
+        match serde_json::from_value(suggestion).unwrap() {
+            BotMessage::Suggestion { moves } => assert_eq!(moves[0].spin, no_spin()),
+            _ => panic!("not a suggestion"),
+        }
+
+        let unknown = serde_json::from_value(json!({"type": "thinking"})).unwrap();
+        assert!(matches!(unknown, BotMessage::Unknown));
+    }
+
+    #[test]
+    fn plays_against_the_reference_bot() {
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let bot = Bot {
+            weights: Weights::default(),
+            lookahead: false,
+        };
+
+        let server = thread::spawn(move || {
+            let mut connection = Connection::socket(theirs).unwrap();
+            serve_connection(&mut connection, &bot)
+        });
+
+        // Taller than the bot looks, so that its rows are counted from
+        // partway up the tower.
+        let world = (0..40)
+            .map(|y| {
+                let mut row = vec!['#'; 10];
+                row[y * 3 % 10] = '.';
+                garbage_row(&row.into_iter().collect::<String>())
+            })
+            .collect();
+        let game = test_game(10, world);
+        let piece_count = game.pieces.len();
+        let mut play = Play::new(game, Randomizer::new(1, piece_count), 0);
+        play.deal();
+
+        let reader = ours.try_clone().unwrap();
+        let mut external =
+            External::greet(Connection::to_bot(reader, ours), None, &play.game).unwrap();
+        assert_eq!(
+            external.name,
+            format!("infinitris {}", env!("CARGO_PKG_VERSION"))
+        );
+
+        for _ in 0..20 {
+            let path = external.plan(&play).unwrap();
+            let target = external.target.map(|(_, spot)| spot);
+            assert!(target.is_some());
+            assert!(external.floor > 0);
+
+            let mut events = Vec::new();
+            for (spot, input) in path.iter() {
+                assert!(Spot::of(&play.game) == *spot);
+                play.tick(&[*input], &mut events);
+            }
+
+            let locked = events
+                .iter()
+                .find(|event| matches!(event, GameEvent::Locked { .. }));
+            let Some(GameEvent::Locked { x, y, orient, .. }) = locked else {
+                panic!("the path didn't lock the piece");
+            };
+
+            let spot = Spot {
+                x: *x,
+                y: *y,
+                orient: *orient,
+            };
+            assert!(target == Some(spot));
+            external.locked(&play, locked.unwrap()).unwrap();
+        }
+
+        external.restart().unwrap();
+        drop(external);
+        assert!(server.join().unwrap().is_ok());
+    }
+}
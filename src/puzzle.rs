@@ -0,0 +1,314 @@
+use std::fs::read_to_string;
+use std::fs::rename;
+use std::fs::write;
+use std::io::ErrorKind;
+use std::rc::Rc;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::fumen::decode_world;
+use crate::fumen::FUMEN_WIDTH;
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::DEFAULT_WIDTH;
+use crate::game::MAX_WIDTH;
+use crate::game::MIN_WIDTH;
+use crate::pieces::PieceSet;
+use crate::play::Goal;
+use crate::play::Play;
+use crate::play::Randomizer;
+use crate::play::WELL_HEIGHT;
+use crate::world::parse_rows;
+
+static BUILTIN_PACKS: [(&str, &str); 1] = [("basics", include_str!("puzzles/basics.toml"))];
+
+const PROGRESS_PATH: &str = "infinitris.puzzles";
+const PROGRESS_TMP_PATH: &str = ".infinitris.puzzles.tmp";
+
+/// What solves a puzzle.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// Number of lines to clear.
+    Lines(u32),
+    /// Clearing every cell off the board.
+    PerfectClear,
+    /// Rows of the starting board which may remain.
+    Depth(u32),
+}
+
+impl Objective {
+    pub fn describe(&self) -> String {
+        match self {
+            Objective::Lines(1) => "CLEAR A LINE".to_string(),
+            Objective::Lines(lines) => format!("CLEAR {} LINES", lines),
+            Objective::PerfectClear => "PERFECT CLEAR".to_string(),
+            Objective::Depth(0) => "CLEAR THE BOARD".to_string(),
+            Objective::Depth(rows) => format!("LEAVE {} ROWS", rows),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    name: Option<String>,
+    pieces: Option<String>,
+    width: Option<usize>,
+    puzzle: Vec<PuzzleFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+    name: Option<String>,
+    /// Rows drawn like in a world file, top row first.
+    board: Option<String>,
+    /// A fumen to take the board from instead, and its page.
+    fumen: Option<String>,
+    page: Option<usize>,
+    /// Piece names in the order they are dealt.
+    queue: Vec<String>,
+    /// "lines", "perfect-clear" or "depth".
+    objective: String,
+    lines: Option<u32>,
+    depth: Option<u32>,
+    /// Pieces which may be placed, all of the queue by default.
+    moves: Option<u32>,
+}
+
+/// An authored challenge: a board, the pieces to solve it with and what
+/// solves it.
+pub struct Puzzle {
+    pub name: String,
+    /// Rows from the bottom up, made of initial garbage.
+    pub world: Vec<Vec<Cell>>,
+    pub queue: Vec<usize>,
+    pub objective: Objective,
+    pub moves: u32,
+}
+
+impl Puzzle {
+    fn parse(file: PuzzleFile, pieces: &PieceSet, width: usize) -> Result<Puzzle, String> {
+        let world = match (&file.board, &file.fumen) {
+            (Some(_), Some(_)) => return Err("both a board and a fumen".to_string()),
+            (_, Some(_)) if width != FUMEN_WIDTH => {
+                return Err(format!("fumen boards are {} cells wide", FUMEN_WIDTH));
+            }
+            (_, Some(fumen)) => decode_world(fumen, file.page.unwrap_or(1))?,
+            (board, None) => parse_rows(board.as_deref().unwrap_or(""), width)
+                .map_err(|e| format!("board {}", e))?,
+        };
+
+        if world.len() >= WELL_HEIGHT {
+            return Err(format!(
+                "{} rows don't fit in a well {} rows high",
+                world.len(),
+                WELL_HEIGHT
+            ));
+        }
+
+        let queue = file
+            .queue
+            .iter()
+            .map(|name| {
+                pieces
+                    .names
+                    .iter()
+                    .position(|n| n == name)
+                    .ok_or_else(|| format!("no piece {:?} in {}", name, pieces.name))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        if queue.is_empty() {
+            return Err("empty queue".to_string());
+        }
+
+        let moves = file.moves.unwrap_or(queue.len() as u32);
+        if moves == 0 || moves as usize > queue.len() {
+            return Err(format!(
+                "moves must be between 1 and the {} queued pieces",
+                queue.len()
+            ));
+        }
+
+        let objective = match file.objective.as_str() {
+            "lines" => Objective::Lines(file.lines.unwrap_or(1)),
+            "perfect-clear" => Objective::PerfectClear,
+            "depth" => Objective::Depth(file.depth.unwrap_or(0)),
+            name => return Err(format!("unknown objective {:?}", name)),
+        };
+
+        match objective {
+            Objective::Lines(0) => return Err("lines must be positive".to_string()),
+
+            Objective::Depth(rows) if rows as usize >= world.len() => {
+                return Err(format!(
+                    "depth must be below the {} rows of the board",
+                    world.len()
+                ));
+            }
+
+            _ => {}
+        }
+
+        Ok(Puzzle {
+            name: file.name.unwrap_or_default(),
+            world,
+            queue,
+            objective,
+            moves,
+        })
+    }
+
+    /// Sets up the puzzle in a game which has been started in a well, keeping
+    /// everything but the board.
+    pub fn play(&self, mut game: Game, seed: u64, undo_depth: usize) -> Play {
+        game.world = self.world.clone();
+        game.layer = game.garbage_left();
+
+        let randomizer = Randomizer::new(seed, game.pieces.len()).with_queue(&self.queue);
+        let mut play = Play::new(game, randomizer, undo_depth);
+        play.goal = Some(Goal::Puzzle {
+            objective: self.objective,
+            moves: self.moves,
+        });
+        play
+    }
+}
+
+/// Puzzles played one after another, on the same board width and with the
+/// same pieces.
+pub struct Pack {
+    pub name: String,
+    pub pieces: Rc<PieceSet>,
+    pub width: usize,
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl Pack {
+    /// Loads a built-in pack by name, or a pack from a TOML file.
+    pub fn load(name: &str) -> Result<Pack, String> {
+        let error = |e: String| format!("{}: {}", name, e);
+
+        let text = match BUILTIN_PACKS.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, text)) => text.to_string(),
+            None => read_to_string(name).map_err(|e| error(e.to_string()))?,
+        };
+
+        let file: PackFile = toml::from_str(&text).map_err(|e| error(e.to_string()))?;
+
+        let pieces =
+            PieceSet::load(file.pieces.as_deref().unwrap_or("tetrominoes")).map_err(error)?;
+        let width = file.width.unwrap_or(DEFAULT_WIDTH);
+
+        if !(MIN_WIDTH..=MAX_WIDTH).contains(&width) {
+            return Err(error(format!(
+                "width must be between {} and {}",
+                MIN_WIDTH, MAX_WIDTH
+            )));
+        }
+
+        if file.puzzle.is_empty() {
+            return Err(error("no puzzles".to_string()));
+        }
+
+        let mut puzzles = Vec::new();
+
+        for (index, puzzle) in file.puzzle.into_iter().enumerate() {
+            let mut puzzle = Puzzle::parse(puzzle, &pieces, width)
+                .map_err(|e| error(format!("puzzle {}: {}", index + 1, e)))?;
+
+            if puzzle.name.is_empty() {
+                puzzle.name = (index + 1).to_string();
+            }
+
+            // Progress is kept by name.
+            if puzzles.iter().any(|p: &Puzzle| p.name == puzzle.name) {
+                return Err(error(format!(
+                    "puzzle {}: name {:?} is taken",
+                    index + 1,
+                    puzzle.name
+                )));
+            }
+
+            puzzles.push(puzzle);
+        }
+
+        Ok(Pack {
+            name: file.name.unwrap_or_else(|| name.to_string()),
+            pieces: Rc::new(pieces),
+            width,
+            puzzles,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Solved {
+    pack: String,
+    puzzle: String,
+    /// When the puzzle was first solved, in seconds since the Unix epoch.
+    time: u64,
+}
+
+/// Puzzles solved so far, in every pack.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Progress {
+    #[serde(default)]
+    solved: Vec<Solved>,
+}
+
+impl Progress {
+    pub fn load() -> Result<Progress, String> {
+        let error = |e: String| format!("{}: {}", PROGRESS_PATH, e);
+
+        match read_to_string(PROGRESS_PATH) {
+            Ok(text) => toml::from_str(&text).map_err(|e| error(e.to_string())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Progress::default()),
+            Err(e) => Err(error(e.to_string())),
+        }
+    }
+
+    fn save(&self) {
+        write(PROGRESS_TMP_PATH, toml::to_string(self).unwrap()).unwrap();
+        rename(PROGRESS_TMP_PATH, PROGRESS_PATH).unwrap();
+    }
+
+    pub fn is_solved(&self, pack: &Pack, puzzle: &Puzzle) -> bool {
+        self.solved
+            .iter()
+            .any(|solved| solved.pack == pack.name && solved.puzzle == puzzle.name)
+    }
+
+    /// Counts the puzzles of a pack which have been solved.
+    pub fn count(&self, pack: &Pack) -> usize {
+        pack.puzzles
+            .iter()
+            .filter(|puzzle| self.is_solved(pack, puzzle))
+            .count()
+    }
+
+    /// Index of the first puzzle in the pack which hasn't been solved, or the
+    /// first one if all have.
+    pub fn first_unsolved(&self, pack: &Pack) -> usize {
+        pack.puzzles
+            .iter()
+            .position(|puzzle| !self.is_solved(pack, puzzle))
+            .unwrap_or(0)
+    }
+
+    pub fn solve(&mut self, pack: &Pack, puzzle: &Puzzle, time: u64) {
+        if !self.is_solved(pack, puzzle) {
+            self.solved.push(Solved {
+                pack: pack.name.clone(),
+                puzzle: puzzle.name.clone(),
+                time,
+            });
+
+            self.save();
+        }
+    }
+}
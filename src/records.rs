@@ -0,0 +1,119 @@
+use std::fs::read_to_string;
+use std::fs::rename;
+use std::fs::write;
+use std::io::ErrorKind;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::play::Goal;
+
+const RECORDS_PATH: &str = "infinitris.records";
+const RECORDS_TMP_PATH: &str = ".infinitris.records.tmp";
+
+/// Conditions which a result is only comparable under.
+pub struct Category {
+    pub goal: Goal,
+    pub width: usize,
+    pub pieces: String,
+    /// Description of the world generator.
+    pub world: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    mode: String,
+    target: u32,
+    width: usize,
+    pieces: String,
+    world: String,
+    /// Ticks taken in a sprint or a dig, or score in an ultra.
+    result: u32,
+    /// When the record was set, in seconds since the Unix epoch.
+    time: u64,
+}
+
+/// Mode name and target of a goal, as stored.
+fn goal_key(goal: Goal) -> (&'static str, u32) {
+    match goal {
+        Goal::Lines(lines) => ("sprint", lines),
+        Goal::Time(seconds) => ("ultra", seconds),
+        Goal::Depth(rows) => ("dig", rows),
+        Goal::Puzzle { moves, .. } => ("puzzle", moves),
+    }
+}
+
+impl Record {
+    fn is(&self, category: &Category) -> bool {
+        let (mode, target) = goal_key(category.goal);
+
+        self.mode == mode
+            && self.target == target
+            && self.width == category.width
+            && self.pieces == category.pieces
+            && self.world == category.world
+    }
+}
+
+/// Personal bests of the timed modes.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Records {
+    #[serde(default, rename = "record")]
+    records: Vec<Record>,
+}
+
+impl Records {
+    pub fn load() -> Result<Records, String> {
+        let error = |e: String| format!("{}: {}", RECORDS_PATH, e);
+
+        match read_to_string(RECORDS_PATH) {
+            Ok(text) => toml::from_str(&text).map_err(|e| error(e.to_string())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Records::default()),
+            Err(e) => Err(error(e.to_string())),
+        }
+    }
+
+    fn save(&self) {
+        write(RECORDS_TMP_PATH, toml::to_string(self).unwrap()).unwrap();
+        rename(RECORDS_TMP_PATH, RECORDS_PATH).unwrap();
+    }
+
+    pub fn best(&self, category: &Category) -> Option<u32> {
+        self.records
+            .iter()
+            .find(|record| record.is(category))
+            .map(|record| record.result)
+    }
+
+    /// Keeps the result if it beats the best one so far.  Returns true if it
+    /// did.
+    pub fn submit(&mut self, category: &Category, result: u32, time: u64) -> bool {
+        let better = match (category.goal, self.best(category)) {
+            (_, None) => true,
+            (Goal::Time(_), Some(best)) => result > best,
+            (_, Some(best)) => result < best,
+        };
+
+        if better {
+            self.records.retain(|record| !record.is(category));
+
+            let (mode, target) = goal_key(category.goal);
+
+            self.records.push(Record {
+                mode: mode.to_string(),
+                target,
+                width: category.width,
+                pieces: category.pieces.clone(),
+                world: category.world.clone(),
+                result,
+                time,
+            });
+
+            self.save();
+        }
+
+        better
+    }
+}
@@ -0,0 +1,802 @@
+use std::f64;
+use std::time::Instant;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::video::FullscreenType;
+
+use crate::anim::Animations;
+use crate::anim::Effect;
+use crate::camera::Camera;
+use crate::font::render_text;
+use crate::font::text_width;
+use crate::game::drop_distance;
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::UNKNOWN_PIECE;
+use crate::layout::Layout;
+use crate::layout::WORLD_ZOOM;
+use crate::legend::render_legend;
+use crate::pieces::Piece;
+use crate::search::Spot;
+use crate::theme::BlockStyle;
+use crate::theme::Theme;
+
+pub const CELL_BORDER: i32 = 1;
+const CONFETTI_PIECES: u32 = 80;
+/// Opacity of the piece drawn where a hint suggests it goes.
+const HINT_ALPHA: u8 = 96;
+
+pub type Canvas = sdl2::render::Canvas<sdl2::video::Window>;
+
+pub fn toggle_fullscreen(canvas: &mut Canvas) {
+    let window = canvas.window_mut();
+
+    let fullscreen = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+
+    window.set_fullscreen(fullscreen).unwrap();
+}
+
+fn piece_color(style: &Style, game: &Game, index: usize) -> Color {
+    let theme = &style.theme;
+
+    if theme.pieces.is_empty() {
+        let [r, g, b] = game.pieces.colors[index];
+        return Color::RGB(r, g, b);
+    }
+
+    // Sets with more pieces than the theme has colours go through them
+    // again in other shades, darker and lighter in turn, so that pieces
+    // stay apart by brightness if not by hue.
+    let color = theme.pieces[index % theme.pieces.len()];
+    let shade = |c: u8| match index / theme.pieces.len() % 4 {
+        0 => c,
+        1 => (c as f64 * 0.55) as u8,
+        2 => (c as f64 + (255 - c) as f64 * 0.5) as u8,
+        _ => (c as f64 * 0.3) as u8,
+    };
+
+    Color::RGB(shade(color.r), shade(color.g), shade(color.b))
+}
+
+/// How placed cells are coloured.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Gradient by generation, showing the strata of the tower.
+    Age,
+    /// The colour of the piece each cell came from.
+    Classic,
+    /// Piece colours, darkened with age.
+    Hybrid,
+}
+
+impl ColorMode {
+    pub fn parse(name: &str) -> Option<ColorMode> {
+        match name {
+            "age" => Some(ColorMode::Age),
+            "classic" => Some(ColorMode::Classic),
+            "hybrid" => Some(ColorMode::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> ColorMode {
+        match self {
+            ColorMode::Age => ColorMode::Classic,
+            ColorMode::Classic => ColorMode::Hybrid,
+            ColorMode::Hybrid => ColorMode::Age,
+        }
+    }
+}
+
+/// What the age gradient measures.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AgeScheme {
+    /// Generation relative to the newest one.
+    Linear,
+    /// Generations ago on a logarithmic scale, which keeps the recent layers
+    /// apart however old the tower gets.
+    Log,
+    /// Wall-clock time of placement, from the first recorded one until now.
+    Time,
+    /// A stripe per play session.
+    Session,
+}
+
+impl AgeScheme {
+    pub fn parse(name: &str) -> Option<AgeScheme> {
+        match name {
+            "linear" => Some(AgeScheme::Linear),
+            "log" => Some(AgeScheme::Log),
+            "time" => Some(AgeScheme::Time),
+            "session" => Some(AgeScheme::Session),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> AgeScheme {
+        match self {
+            AgeScheme::Linear => AgeScheme::Log,
+            AgeScheme::Log => AgeScheme::Time,
+            AgeScheme::Time => AgeScheme::Session,
+            AgeScheme::Session => AgeScheme::Linear,
+        }
+    }
+}
+
+/// Presentation choices which can be changed while playing.
+pub struct Style {
+    pub color_mode: ColorMode,
+    pub age_scheme: AgeScheme,
+    pub legend: bool,
+    pub theme: Theme,
+}
+
+/// Spread of the history which the age schemes map onto the gradient.
+/// Measured once per frame.
+pub struct AgeRange {
+    /// Oldest known placement time.
+    pub first_time: u64,
+}
+
+impl AgeRange {
+    pub fn new(game: &Game) -> AgeRange {
+        AgeRange {
+            first_time: game
+                .history
+                .iter()
+                .map(|g| g.time)
+                .find(|time| *time != 0)
+                .unwrap_or(game.time),
+        }
+    }
+}
+
+/// Colour at position `t` (between 0 and 1, from old to new) along the age
+/// gradient.
+pub fn gradient_color(theme: &Theme, t: f64) -> Color {
+    if !theme.age.is_empty() {
+        return gradient_stop_color(&theme.age, t);
+    }
+
+    Color::RGB(
+        (64.0 + (0.5 * f64::consts::PI * t).sin() * 127.0) as u8,
+        (160.0 * t + 32.0 * (64.0 * f64::consts::PI * t).sin()) as u8,
+        (64.0 + (0.5 * f64::consts::PI * t).cos() * 127.0) as u8,
+    )
+}
+
+/// Colour of a session's stripe.  Neighbouring stripes are told apart by
+/// brightness in case the gradient barely changes between them.
+pub fn session_color(theme: &Theme, session: u32, sessions: u32) -> Color {
+    let color = gradient_color(theme, session as f64 / u32::max(sessions, 1) as f64);
+
+    if session.is_multiple_of(2) {
+        color
+    } else {
+        Color::RGB(
+            (color.r as f64 * 0.75) as u8,
+            (color.g as f64 * 0.75) as u8,
+            (color.b as f64 * 0.75) as u8,
+        )
+    }
+}
+
+fn age_color(style: &Style, game: &Game, range: &AgeRange, gen: u32) -> Color {
+    let generation = game.history.get(gen as usize).copied().unwrap_or_default();
+
+    let t = match style.age_scheme {
+        AgeScheme::Linear => gen as f64 / game.next_gen as f64,
+
+        AgeScheme::Log => {
+            let age = game.next_gen.saturating_sub(gen + 1) as f64;
+            let max_age = game.next_gen.saturating_sub(2) as f64;
+
+            if max_age > 0.0 {
+                1.0 - (1.0 + age).ln() / (1.0 + max_age).ln()
+            } else {
+                1.0
+            }
+        }
+
+        AgeScheme::Time => {
+            let span = game.time.saturating_sub(range.first_time) as f64;
+            let time = generation.time.max(range.first_time);
+
+            if span > 0.0 {
+                (time - range.first_time) as f64 / span
+            } else {
+                1.0
+            }
+        }
+
+        AgeScheme::Session => {
+            return session_color(&style.theme, generation.session, game.session);
+        }
+    };
+
+    gradient_color(&style.theme, t)
+}
+
+/// Colour at position `t` (between 0 and 1) along evenly spaced stops.
+fn gradient_stop_color(stops: &[Color], t: f64) -> Color {
+    let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = usize::min(pos as usize, stops.len() - 2);
+    let f = pos - i as f64;
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    let (a, b) = (stops[i], stops[i + 1]);
+
+    Color::RGB(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b))
+}
+
+/// Colour of a placed cell's piece, or the garbage colour for garbage and
+/// cells from old saves.
+fn classic_color(style: &Style, game: &Game, cell: &Cell) -> Color {
+    if cell.piece != UNKNOWN_PIECE && (cell.piece as usize) < game.pieces.len() {
+        piece_color(style, game, cell.piece as usize)
+    } else {
+        style.theme.garbage
+    }
+}
+
+fn cell_color(style: &Style, game: &Game, range: &AgeRange, cell: &Cell) -> Color {
+    match style.color_mode {
+        ColorMode::Age => age_color(style, game, range, cell.gen),
+        ColorMode::Classic => classic_color(style, game, cell),
+
+        ColorMode::Hybrid => {
+            let color = classic_color(style, game, cell);
+            let shade = 0.35 + 0.65 * cell.gen as f64 / game.next_gen as f64;
+
+            Color::RGB(
+                (color.r as f64 * shade) as u8,
+                (color.g as f64 * shade) as u8,
+                (color.b as f64 * shade) as u8,
+            )
+        }
+    }
+}
+
+/// Size of a world cell on screen.
+pub fn cell_size(layout: &Layout, camera: &Camera) -> i32 {
+    (layout.cell as f64 * camera.zoom).round().max(1.0) as i32
+}
+
+/// Screen position of a level (the bottom edge of a row) in the world.
+pub fn level_y(layout: &Layout, camera: &Camera, level: f64) -> i32 {
+    layout.surface_y - ((level - camera.level) * cell_size(layout, camera) as f64) as i32
+}
+
+/// Levels at the top and bottom edges of the window.
+pub fn visible_levels(layout: &Layout, camera: &Camera) -> (f64, f64) {
+    let cell = cell_size(layout, camera) as f64;
+    let above = layout.surface_y as f64 / cell;
+    let below = (layout.height - layout.surface_y) as f64 / cell;
+
+    (camera.level + above, camera.level - below)
+}
+
+/// World cell (x, y) at a screen position, if there is one.
+pub fn cell_at(
+    layout: &Layout,
+    game: &Game,
+    camera: &Camera,
+    x: i32,
+    y: i32,
+) -> Option<(usize, usize)> {
+    let cell = cell_size(layout, camera);
+    let x = x - layout.left;
+    if x < 0 || x >= game.width as i32 * cell {
+        return None;
+    }
+
+    let level = camera.level + (layout.surface_y - y) as f64 / cell as f64;
+    if level < 0.0 || level >= game.world.len() as f64 {
+        return None;
+    }
+
+    Some(((x / cell) as usize, level as usize))
+}
+
+/// Height of a world row in the minimap.  While exploring, the minimap is
+/// squeezed to fit the whole tower.
+fn minimap_row_height(layout: &Layout, game: &Game, camera: &Camera) -> f64 {
+    let full = layout.cell as f64 / WORLD_ZOOM as f64;
+
+    if camera.explore && !game.world.is_empty() {
+        f64::min(
+            full,
+            (layout.height - layout.minimap_y) as f64 / game.world.len() as f64,
+        )
+    } else {
+        full
+    }
+}
+
+/// World row shown at a screen position within the minimap.
+pub fn minimap_row_at(
+    layout: &Layout,
+    game: &Game,
+    camera: &Camera,
+    x: i32,
+    y: i32,
+) -> Option<usize> {
+    if x < layout.panel_x || x >= layout.panel_x + layout.minimap_width || y < layout.minimap_y {
+        return None;
+    }
+
+    let j = ((y - layout.minimap_y) as f64 / minimap_row_height(layout, game, camera)) as usize;
+    if j < game.world.len() {
+        Some(game.world.len() - 1 - j)
+    } else {
+        None
+    }
+}
+
+/// Everything that changes from frame to frame.
+pub struct Scene<'a> {
+    pub game: &'a Game,
+    pub anims: &'a Animations,
+    pub camera: &'a Camera,
+    /// Part of the last fall step not yet shown, between 0 and 1.
+    pub fall: f64,
+    pub now: Instant,
+    /// Whether to draw the active piece, its shadow and the next piece.
+    pub piece: bool,
+    /// Whether there is a next piece to draw along with the active one.
+    pub next: bool,
+    /// Where a hint suggests the active piece should go.
+    pub hint: Option<Spot>,
+}
+
+pub fn render_game(canvas: &mut Canvas, layout: &Layout, style: &Style, scene: &Scene) {
+    let Scene {
+        game,
+        anims,
+        camera,
+        fall,
+        now,
+        piece: show_piece,
+        next: show_next,
+        hint,
+    } = *scene;
+
+    canvas.set_draw_color(style.theme.background);
+    canvas.clear();
+
+    let piece = game.effective_piece();
+    let range = AgeRange::new(game);
+    let cell = cell_size(layout, camera);
+    let level_y = |level: f64| level_y(layout, camera, level);
+    let cell_x = |x: usize| layout.left + x as i32 * cell;
+
+    // Screen position of the top edge of a world row, following collapsing
+    // line clears.
+    let row_y = |y: usize| level_y((y + 1) as f64 + anims.collapse_shift(y, now));
+
+    for anim in anims.list.iter() {
+        if let Effect::RowClear { rows } = &anim.effect {
+            let alpha = 255.0 * (1.0 - anim.collapse(now));
+
+            for (k, boundary) in rows.iter().enumerate() {
+                let stacked = rows[k..].iter().filter(|y| *y == boundary).count();
+                let below = match boundary {
+                    0 => 0.0,
+                    _ => anims.collapse_shift(boundary - 1, now),
+                };
+
+                canvas.set_draw_color(Color::RGBA(255, 255, 255, alpha as u8));
+                canvas
+                    .fill_rect(Rect::new(
+                        layout.left,
+                        level_y((boundary + stacked) as f64 + below),
+                        game.width as u32 * cell as u32,
+                        cell as u32,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+
+    for (y, row) in game.world.iter().enumerate() {
+        let row_y = row_y(y);
+        if row_y >= layout.height || row_y + cell < 0 {
+            continue;
+        }
+
+        for (i, world_cell) in row.iter().enumerate() {
+            if !world_cell.is_empty() {
+                let color = cell_color(style, game, &range, world_cell);
+                render_block(canvas, style, cell as u32, cell_x(i), row_y, color);
+            }
+        }
+    }
+
+    for anim in anims.list.iter() {
+        if let Effect::LockFlash { cells } = &anim.effect {
+            let alpha = 191.0 * (1.0 - anim.progress(now));
+            canvas.set_draw_color(Color::RGBA(255, 255, 255, alpha as u8));
+
+            for (x, y) in cells.iter() {
+                canvas
+                    .fill_rect(Rect::new(cell_x(*x), row_y(*y), cell as u32, cell as u32))
+                    .unwrap();
+            }
+        }
+    }
+
+    canvas.set_draw_color(style.theme.panel);
+
+    canvas
+        .fill_rect(Rect::new(
+            layout.left,
+            level_y(0.0),
+            game.width as u32 * cell as u32,
+            layout.height as u32,
+        ))
+        .unwrap();
+
+    canvas
+        .fill_rect(Rect::new(
+            layout.left + layout.board_width,
+            0,
+            layout.gap as u32,
+            layout.height as u32,
+        ))
+        .unwrap();
+
+    // Pieces which lock above the well's rim end the game.
+    if let Some(height) = game.well {
+        canvas
+            .fill_rect(Rect::new(
+                layout.left,
+                level_y(height as f64) - layout.gap / 2,
+                game.width as u32 * cell as u32,
+                u32::max(layout.gap as u32 / 2, 1),
+            ))
+            .unwrap();
+    }
+
+    render_minimap(canvas, layout, style, game, &range, camera);
+
+    for anim in anims.list.iter() {
+        if let Effect::DropTrail {
+            piece_index,
+            orient,
+            x,
+            from_y,
+            to_y,
+        } = anim.effect
+        {
+            let dropped = game.pieces.effective_piece(piece_index, orient);
+            let color = piece_color(style, game, piece_index);
+            let alpha = 127.0 * (1.0 - anim.progress(now));
+            canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, alpha as u8));
+
+            for i in 0..dropped.width {
+                // The trail ends at the topmost cell of each column.
+                let top = (0..dropped.height).find(|j| dropped.cells[*j][i]).unwrap();
+                let top_y = |y: usize| row_y(y + dropped.height - 1 - top);
+
+                canvas
+                    .fill_rect(Rect::new(
+                        cell_x(x + i),
+                        top_y(from_y),
+                        cell as u32,
+                        (top_y(to_y) - top_y(from_y)) as u32,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+
+    if show_piece {
+        let piece_top = game.y + piece.height;
+        let shadow_distance = drop_distance(game);
+
+        render_piece(
+            canvas,
+            style,
+            cell as usize,
+            cell_x(game.x),
+            level_y((piece_top - shadow_distance) as f64),
+            &piece,
+            style.theme.shadow,
+        );
+
+        if let Some(spot) = hint {
+            let hinted = game.pieces.effective_piece(game.piece_index, spot.orient);
+            let color = piece_color(style, game, game.piece_index);
+
+            render_piece(
+                canvas,
+                style,
+                cell as usize,
+                cell_x(spot.x),
+                level_y((spot.y + hinted.height) as f64),
+                &hinted,
+                Color::RGBA(color.r, color.g, color.b, HINT_ALPHA),
+            );
+        }
+
+        render_piece(
+            canvas,
+            style,
+            cell as usize,
+            cell_x(game.x),
+            level_y(piece_top as f64 + fall),
+            &piece,
+            piece_color(style, game, game.piece_index),
+        );
+
+        if show_next {
+            render_piece(
+                canvas,
+                style,
+                layout.next_cell as usize,
+                layout.panel_x,
+                layout.gap,
+                &game.pieces.pieces[game.next_piece_index],
+                piece_color(style, game, game.next_piece_index),
+            );
+        }
+    }
+
+    if style.legend && style.color_mode == ColorMode::Age {
+        render_legend(canvas, layout, style, game);
+    }
+
+    render_milestones(canvas, layout, style, game, anims, now);
+}
+
+/// Pseudo-random fraction for the nth confetti piece, so that every frame
+/// agrees on where the pieces are.
+fn scatter(n: u32) -> f64 {
+    (n.wrapping_mul(2654435761) >> 8) as f64 / (1 << 24) as f64
+}
+
+fn render_milestones(
+    canvas: &mut Canvas,
+    layout: &Layout,
+    style: &Style,
+    game: &Game,
+    anims: &Animations,
+    now: Instant,
+) {
+    let scale = 2 * layout.text_scale();
+
+    for anim in anims.list.iter() {
+        let t = anim.progress(now);
+
+        match anim.effect {
+            Effect::Confetti => {
+                let size = u32::max(2, layout.cell as u32 / 4);
+
+                for n in 0..CONFETTI_PIECES {
+                    let speed = 0.75 + scatter(3 * n + 1) / 2.0;
+                    let sway =
+                        (t * 8.0 + scatter(3 * n + 2) * 6.0).sin() * layout.cell as f64 / 2.0;
+                    let x = layout.left as f64 + scatter(3 * n) * layout.board_width as f64 + sway;
+                    let y = (t * speed * 1.5 - scatter(3 * n + 2) / 2.0) * layout.height as f64;
+
+                    canvas.set_draw_color(piece_color(style, game, n as usize % game.pieces.len()));
+                    canvas
+                        .fill_rect(Rect::new(x as i32, y as i32, size, size))
+                        .unwrap();
+                }
+            }
+
+            Effect::Milestone { left } => {
+                let text = match left {
+                    0 => "ORIGINAL LAYER CLEARED".to_string(),
+                    1 => "1 ROW TO GO".to_string(),
+                    _ => format!("{} ROWS TO GO", left),
+                };
+
+                // Fade out over the last quarter.
+                let alpha = 255.0 * f64::min(1.0, 4.0 * (1.0 - t));
+                let x = layout.left + (layout.board_width - text_width(scale, &text) as i32) / 2;
+
+                render_text(
+                    canvas,
+                    x,
+                    layout.height / 4,
+                    scale,
+                    Color::RGBA(255, 255, 255, alpha as u8),
+                    &text,
+                );
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Bar in the side panel showing how much of the initial garbage has been
+/// cleared.
+pub fn render_progress(canvas: &mut Canvas, layout: &Layout, style: &Style, dug: f64) {
+    let y = layout.minimap_y - 3 * layout.gap;
+
+    canvas.set_draw_color(style.theme.panel);
+    canvas
+        .fill_rect(Rect::new(
+            layout.panel_x,
+            y,
+            layout.minimap_width as u32,
+            layout.gap as u32,
+        ))
+        .unwrap();
+
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas
+        .fill_rect(Rect::new(
+            layout.panel_x,
+            y,
+            (dug * layout.minimap_width as f64) as u32,
+            layout.gap as u32,
+        ))
+        .unwrap();
+}
+
+fn render_minimap(
+    canvas: &mut Canvas,
+    layout: &Layout,
+    style: &Style,
+    game: &Game,
+    range: &AgeRange,
+    camera: &Camera,
+) {
+    let row_height = minimap_row_height(layout, game, camera);
+    let block_width = layout.minimap_cell as u32;
+    let block_height = row_height.ceil() as u32;
+    let mut last_y = None;
+
+    for (j, row) in game.world.iter().rev().enumerate() {
+        let y = layout.minimap_y + (j as f64 * row_height) as i32;
+        if y >= layout.height {
+            break;
+        }
+
+        // When squeezed, draw only one row per pixel line.
+        if last_y == Some(y) {
+            continue;
+        }
+        last_y = Some(y);
+
+        for (i, cell) in row.iter().enumerate() {
+            if !cell.is_empty() {
+                let x = layout.panel_x + (i as u32 * block_width) as i32;
+                let color = cell_color(style, game, range, cell);
+
+                if block_height >= block_width {
+                    render_block(canvas, style, block_width, x, y, color);
+                } else {
+                    canvas.set_draw_color(color);
+                    canvas
+                        .fill_rect(Rect::new(x, y, block_width, block_height))
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    canvas.set_draw_color(style.theme.panel);
+
+    canvas
+        .fill_rect(Rect::new(
+            layout.panel_x,
+            layout.minimap_y + (game.world.len() as f64 * row_height) as i32,
+            layout.minimap_width as u32,
+            layout.height as u32,
+        ))
+        .unwrap();
+
+    if camera.explore {
+        let (top, bottom) = visible_levels(layout, camera);
+        let minimap_y =
+            |level: f64| layout.minimap_y + ((game.world.len() as f64 - level) * row_height) as i32;
+
+        canvas.set_draw_color(Color::RGBA(255, 255, 255, 191));
+        canvas
+            .draw_rect(Rect::new(
+                layout.panel_x,
+                minimap_y(top),
+                layout.minimap_width as u32,
+                (minimap_y(bottom) - minimap_y(top)).max(1) as u32,
+            ))
+            .unwrap();
+    }
+}
+
+fn render_piece(
+    canvas: &mut Canvas,
+    style: &Style,
+    size: usize,
+    x: i32,
+    y: i32,
+    piece: &Piece,
+    color: Color,
+) {
+    for (j, row) in piece.cells.iter().enumerate() {
+        for (i, cell) in row.iter().enumerate() {
+            if *cell {
+                render_block(
+                    canvas,
+                    style,
+                    size as u32,
+                    x + (i * size) as i32,
+                    y + (j * size) as i32,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+fn render_block(canvas: &mut Canvas, style: &Style, size: u32, x: i32, y: i32, color: Color) {
+    let rect = Rect::new(x, y, size, size);
+
+    match style.theme.block {
+        BlockStyle::Flat => {
+            canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, 127));
+
+            canvas.fill_rect(rect).unwrap();
+
+            canvas.set_draw_color(color);
+
+            canvas
+                .fill_rect(Rect::new(
+                    x + CELL_BORDER / 2,
+                    y + CELL_BORDER / 2,
+                    size - CELL_BORDER as u32,
+                    size - CELL_BORDER as u32,
+                ))
+                .unwrap();
+        }
+
+        BlockStyle::Bevelled => {
+            let bevel = u32::max(1, size / 8);
+            let inner = size.saturating_sub(2 * bevel).max(1);
+
+            // Light from the top left: the top and left edges are drawn over
+            // a darker full block, and the face over both.
+            canvas.set_draw_color(Color::RGBA(color.r / 2, color.g / 2, color.b / 2, color.a));
+            canvas.fill_rect(rect).unwrap();
+
+            canvas.set_draw_color(Color::RGBA(
+                color.r / 2 + 128,
+                color.g / 2 + 128,
+                color.b / 2 + 128,
+                color.a,
+            ));
+            canvas
+                .fill_rects(&[
+                    Rect::new(x, y, size - bevel, bevel),
+                    Rect::new(x, y, bevel, size - bevel),
+                ])
+                .unwrap();
+
+            canvas.set_draw_color(color);
+            canvas
+                .fill_rect(Rect::new(x + bevel as i32, y + bevel as i32, inner, inner))
+                .unwrap();
+        }
+
+        BlockStyle::Outlined => {
+            canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, color.a / 4));
+            canvas.fill_rect(rect).unwrap();
+
+            canvas.set_draw_color(color);
+            canvas.draw_rect(rect).unwrap();
+
+            if size >= 8 {
+                canvas
+                    .draw_rect(Rect::new(x + 1, y + 1, size - 2, size - 2))
+                    .unwrap();
+            }
+        }
+    }
+}
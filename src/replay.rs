@@ -0,0 +1,463 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::game::MAX_WIDTH;
+use crate::game::MIN_WIDTH;
+use crate::play::Goal;
+use crate::play::Input;
+use crate::play::Play;
+use crate::play::Tick;
+
+const PAUSE: u8 = 5;
+const END: u8 = 0xff;
+
+/// Something the player did, at the tick it took effect.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Input(Input),
+
+    /// The game was paused after the previous tick.  Pausing resets gravity,
+    /// so it matters to what follows.
+    Pause,
+}
+
+impl Action {
+    fn code(self) -> u8 {
+        match self {
+            Action::Input(Input::Left) => 0,
+            Action::Input(Input::Right) => 1,
+            Action::Input(Input::Rotate) => 2,
+            Action::Input(Input::SoftDrop) => 3,
+            Action::Input(Input::HardDrop) => 4,
+            Action::Pause => PAUSE,
+            Action::Input(Input::Undo) => 6,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Action> {
+        match code {
+            0 => Some(Action::Input(Input::Left)),
+            1 => Some(Action::Input(Input::Right)),
+            2 => Some(Action::Input(Input::Rotate)),
+            3 => Some(Action::Input(Input::SoftDrop)),
+            4 => Some(Action::Input(Input::HardDrop)),
+            PAUSE => Some(Action::Pause),
+            6 => Some(Action::Input(Input::Undo)),
+            _ => None,
+        }
+    }
+}
+
+/// Fingerprint of the world, for telling whether a replay ended up where the
+/// recording did.
+pub fn checksum(game: &Game) -> u64 {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    feed(&game.next_gen.to_be_bytes());
+    for row in game.world.iter() {
+        for cell in row.iter() {
+            feed(&cell.gen.to_be_bytes());
+            feed(&[cell.piece]);
+        }
+    }
+
+    hash
+}
+
+/// Writes the player's actions to a replay file as they happen.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    /// Starts a replay of a game which is about to be played from its current
+    /// state.
+    pub fn create(path: &str, seed: u64, play: &Play) -> Result<Recorder, String> {
+        let (goal, target) = match play.goal {
+            None => (0, 0),
+            Some(Goal::Lines(lines)) => (1, lines as u16),
+            Some(Goal::Time(seconds)) => (2, seconds as u16),
+            Some(Goal::Depth(rows)) => (3, rows as u16),
+
+            // The piece queue has no place in the format, so --record is
+            // refused along with --puzzle.
+            Some(Goal::Puzzle { .. }) => return Err("puzzles can't be recorded".to_string()),
+        };
+
+        let mut file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let game = &play.game;
+
+        let undo_depth = usize::min(play.undo_depth, u8::MAX as usize) as u8;
+        let well = game.well.unwrap_or(0) as u8;
+        let [high, low] = target.to_be_bytes();
+        let mut bytes = vec![1, game.width as u8, undo_depth, well, goal, high, low, 0];
+        bytes.extend_from_slice(&seed.to_be_bytes());
+        bytes.push(game.pieces.name.len() as u8);
+        bytes.extend_from_slice(game.pieces.name.as_bytes());
+        bytes.extend_from_slice(&game.next_gen.to_be_bytes());
+        bytes.extend_from_slice(&(game.world.len() as u32).to_be_bytes());
+        for row in game.world.iter() {
+            for cell in row.iter() {
+                bytes.extend_from_slice(&cell.gen.to_be_bytes());
+                bytes.push(cell.piece);
+            }
+        }
+
+        file.write_all(&bytes)
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        Ok(Recorder { file })
+    }
+
+    fn write(&mut self, tick: u32, code: u8) {
+        let mut bytes = [0u8; 5];
+        bytes[..4].copy_from_slice(&tick.to_be_bytes());
+        bytes[4] = code;
+        self.file.write_all(&bytes).unwrap();
+    }
+
+    pub fn record(&mut self, tick: u32, action: Action) {
+        self.write(tick, action.code());
+    }
+
+    /// Marks the end of the game with a checksum of the final world.
+    pub fn finish(&mut self, tick: u32, game: &Game) {
+        self.write(tick, END);
+        self.file.write_all(&checksum(game).to_be_bytes()).unwrap();
+    }
+}
+
+pub struct Replay {
+    pub width: usize,
+    pub seed: u64,
+    pub undo_depth: usize,
+    pub well: Option<usize>,
+    pub goal: Option<Goal>,
+    /// Name of the piece set the game was played with.
+    pub pieces: String,
+    pub next_gen: u32,
+    pub world: Vec<Vec<Cell>>,
+    /// Sorted by tick.
+    pub actions: Vec<(u32, Action)>,
+    /// Tick and world checksum at the end, unless the recording was cut
+    /// short.
+    pub end: Option<(u32, u64)>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        Replay::read(&mut BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn read(file: &mut impl Read) -> Result<Replay, String> {
+        let truncated = |_| "truncated replay".to_string();
+        let mut read = |size: usize| -> Result<Vec<u8>, String> {
+            let mut bytes = vec![0u8; size];
+            file.read_exact(&mut bytes).map_err(truncated)?;
+            Ok(bytes)
+        };
+        let word = |bytes: Vec<u8>| u32::from_be_bytes(bytes[..].try_into().unwrap());
+        let long = |bytes: Vec<u8>| u64::from_be_bytes(bytes[..].try_into().unwrap());
+
+        let header = read(8)?;
+        if header[0] != 1 {
+            return Err(format!("unknown replay version {}", header[0]));
+        }
+
+        let width = header[1] as usize;
+        if !(MIN_WIDTH..=MAX_WIDTH).contains(&width) {
+            return Err(format!("invalid width {}", width));
+        }
+
+        let undo_depth = header[2] as usize;
+        let well = match header[3] {
+            0 => None,
+            height => Some(height as usize),
+        };
+        let target = u16::from_be_bytes([header[5], header[6]]) as u32;
+        let goal = match header[4] {
+            0 => None,
+            1 => Some(Goal::Lines(target)),
+            2 => Some(Goal::Time(target)),
+            3 => Some(Goal::Depth(target)),
+            kind => return Err(format!("unknown goal {}", kind)),
+        };
+        let seed = long(read(8)?);
+        let name_len = read(1)?[0] as usize;
+        let pieces = String::from_utf8(read(name_len)?).map_err(|e| e.to_string())?;
+        let next_gen = word(read(4)?);
+
+        let mut world = Vec::new();
+        for _ in 0..word(read(4)?) {
+            let row = read(width * 5)?
+                .chunks(5)
+                .map(|cell| Cell {
+                    gen: u32::from_be_bytes(cell[0..4].try_into().unwrap()),
+                    piece: cell[4],
+                })
+                .collect();
+            world.push(row);
+        }
+
+        let mut actions = Vec::new();
+        let mut end = None;
+
+        loop {
+            let mut bytes = [0u8; 5];
+            if file.read_exact(&mut bytes).is_err() {
+                break;
+            }
+
+            let tick = u32::from_be_bytes(bytes[..4].try_into().unwrap());
+
+            if bytes[4] == END {
+                let mut sum = [0u8; 8];
+                file.read_exact(&mut sum).map_err(truncated)?;
+                end = Some((tick, u64::from_be_bytes(sum)));
+                break;
+            }
+
+            match Action::from_code(bytes[4]) {
+                Some(action) => actions.push((tick, action)),
+                None => return Err(format!("unknown action {}", bytes[4])),
+            }
+        }
+
+        Ok(Replay {
+            width,
+            seed,
+            undo_depth,
+            well,
+            goal,
+            pieces,
+            next_gen,
+            world,
+            actions,
+            end,
+        })
+    }
+
+    /// Last tick worth playing.
+    pub fn length(&self) -> u32 {
+        match self.end {
+            Some((tick, _)) => tick,
+            None => self.actions.last().map(|(tick, _)| tick + 1).unwrap_or(0),
+        }
+    }
+}
+
+/// Feeds a replay's actions to a game at the ticks they were recorded at.
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback { replay, next: 0 }
+    }
+
+    pub fn finished(&self, play: &Play) -> bool {
+        play.ticks >= self.replay.length()
+    }
+
+    pub fn tick(&mut self, play: &mut Play, events: &mut Vec<GameEvent>) -> Tick {
+        if self.finished(play) {
+            return Tick::default();
+        }
+
+        let mut inputs = Vec::new();
+
+        while let Some(&(tick, action)) = self.replay.actions.get(self.next) {
+            if tick > play.ticks {
+                break;
+            }
+
+            match action {
+                Action::Input(input) => inputs.push(input),
+                Action::Pause => play.resume(),
+            }
+
+            self.next += 1;
+        }
+
+        play.tick(&inputs, events)
+    }
+
+    /// Once played to the end, whether the world matches the recording's.
+    pub fn verdict(&self, play: &Play) -> Option<bool> {
+        match self.replay.end {
+            Some((_, sum)) if self.finished(play) => Some(sum == checksum(&play.game)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::garbage_row;
+    use crate::game::test_game;
+    use crate::play::Randomizer;
+    use std::env;
+    use std::fs;
+
+    const SEED: u64 = 12345;
+
+    fn new_play(world: Vec<Vec<Cell>>, seed: u64) -> Play {
+        let game = test_game(10, world);
+        let piece_count = game.pieces.len();
+
+        let mut play = Play::new(game, Randomizer::new(seed, piece_count), 0);
+        play.deal();
+        play
+    }
+
+    /// Plays a fixed sequence of inputs on top of a row of garbage and
+    /// returns the recording and the checksum of the final world.
+    fn record(name: &str) -> (Vec<u8>, u64) {
+        let mut play = new_play(vec![garbage_row(".##.##.##.")], SEED);
+
+        let path = env::temp_dir().join(format!("infinitris-{}-{}", name, std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut recorder = Recorder::create(path, SEED, &play).unwrap();
+        let mut events = Vec::new();
+
+        let script = [
+            Input::Left,
+            Input::Left,
+            Input::HardDrop,
+            Input::Rotate,
+            Input::Right,
+            Input::SoftDrop,
+            Input::HardDrop,
+            Input::Right,
+            Input::Right,
+            Input::Right,
+            Input::HardDrop,
+        ];
+
+        for tick in 0..600 {
+            let inputs = match script.get(tick / 10) {
+                Some(input) if tick % 10 == 0 => vec![*input],
+                _ => Vec::new(),
+            };
+
+            for input in inputs.iter() {
+                recorder.record(play.ticks, Action::Input(*input));
+            }
+
+            if tick == 250 {
+                recorder.record(play.ticks, Action::Pause);
+                play.resume();
+            }
+
+            play.tick(&inputs, &mut events);
+        }
+
+        recorder.finish(play.ticks, &play.game);
+        drop(recorder);
+
+        let bytes = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+        (bytes, checksum(&play.game))
+    }
+
+    fn play_back(replay: Replay) -> (Play, Option<bool>) {
+        let mut play = new_play(replay.world.clone(), replay.seed);
+        let mut playback = Playback::new(replay);
+        let mut events = Vec::new();
+
+        while !playback.finished(&play) {
+            playback.tick(&mut play, &mut events);
+        }
+
+        let verdict = playback.verdict(&play);
+        (play, verdict)
+    }
+
+    #[test]
+    fn replay_matches_recording() {
+        let (bytes, sum) = record("matches");
+        let replay = Replay::read(&mut &bytes[..]).unwrap();
+
+        assert_eq!(replay.width, 10);
+        assert_eq!(replay.seed, SEED);
+        assert_eq!(replay.pieces, "tetrominoes");
+        assert_eq!(replay.end.map(|(_, end_sum)| end_sum), Some(sum));
+
+        let (play, verdict) = play_back(replay);
+        assert_eq!(verdict, Some(true));
+        assert_eq!(checksum(&play.game), sum);
+        assert!(play.stats.pieces >= 3);
+    }
+
+    #[test]
+    fn altered_input_differs() {
+        let (mut bytes, _) = record("altered");
+        let mut replay = Replay::read(&mut &bytes[..]).unwrap();
+
+        // Move the first piece right instead of left.
+        replay.actions[0].1 = Action::Input(Input::Right);
+        assert_eq!(play_back(replay).1, Some(false));
+
+        // Corrupting the checksum has the same effect.
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let replay = Replay::read(&mut &bytes[..]).unwrap();
+        assert_eq!(play_back(replay).1, Some(false));
+    }
+
+    #[test]
+    fn corrupted_replay_is_rejected() {
+        let (bytes, _) = record("corrupted");
+
+        let mut version = bytes.clone();
+        version[0] = 2;
+        assert!(Replay::read(&mut &version[..]).is_err());
+
+        let mut width = bytes.clone();
+        width[1] = 0;
+        assert!(Replay::read(&mut &width[..]).is_err());
+
+        let mut goal = bytes.clone();
+        goal[4] = 9;
+        assert!(Replay::read(&mut &goal[..]).is_err());
+
+        // The action code of the first action, after the tick.
+        let header = 8 + 8 + 1 + "tetrominoes".len() + 4 + 4 + 10 * 5;
+        let mut action = bytes.clone();
+        action[header + 4] = 0x7f;
+        assert!(Replay::read(&mut &action[..]).is_err());
+    }
+
+    #[test]
+    fn truncated_replay_is_rejected() {
+        let (bytes, _) = record("truncated");
+
+        for len in [0, 5, 20, 40] {
+            assert!(Replay::read(&mut &bytes[..len]).is_err());
+        }
+
+        // A cut in the middle of the checksum is an error too.
+        assert!(Replay::read(&mut &bytes[..bytes.len() - 3]).is_err());
+
+        // Cut between actions, the recording has no end to compare with.
+        let replay = Replay::read(&mut &bytes[..bytes.len() - 13]).unwrap();
+        assert!(replay.end.is_none());
+        assert_eq!(play_back(replay).1, None);
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+
+use crate::game::detect_collision;
+use crate::game::drop_distance;
+use crate::game::drop_piece;
+use crate::game::move_piece;
+use crate::game::rotate_piece;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::play::Input;
+
+/// Where the active piece is and which way it's turned.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Spot {
+    pub x: usize,
+    pub y: usize,
+    pub orient: u8,
+}
+
+impl Spot {
+    pub fn of(game: &Game) -> Spot {
+        Spot {
+            x: game.x,
+            y: game.y,
+            orient: game.orient,
+        }
+    }
+}
+
+/// A way for the active piece to lock, and a shortest sequence of inputs
+/// which gets it there.
+pub struct Placement {
+    /// Where the piece locks.
+    pub spot: Spot,
+    /// The piece locks as a T-spin.
+    pub spin: bool,
+    /// Inputs from where the piece started, each with where the piece is
+    /// when it's given.  The last one is a hard drop.
+    pub path: Vec<(Spot, Input)>,
+    /// The game once the piece has locked, before the next one spawns, and
+    /// without the history.
+    pub game: Game,
+    /// Rows cleared by the lock.
+    pub lines: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    spot: Spot,
+    /// The last move was a rotation, which decides T-spins.
+    rotated: bool,
+}
+
+fn place(game: &mut Game, state: State) {
+    game.x = state.spot.x;
+    game.y = state.spot.y;
+    game.orient = state.spot.orient;
+    game.rotated = state.rotated;
+}
+
+/// Every way the active piece can lock when moved, turned, soft-dropped and
+/// hard-dropped from where it is, including tucks under overhangs and spins.
+/// Ways which leave the same board count once, unless only one of them is a
+/// T-spin.
+///
+/// A soft drop in a path stands for a single row, as it does when soft drops
+/// are given no more than one per tick.
+pub fn placements(game: &Game) -> Vec<Placement> {
+    let mut probe = Game {
+        history: Vec::new(),
+        ..game.clone()
+    };
+
+    let start = State {
+        spot: Spot::of(game),
+        rotated: game.rotated,
+    };
+
+    // Searched breadth first, so that the first path to anything is a
+    // shortest one.  Each state comes with the state and input it was reached
+    // from.
+    let mut states: Vec<(State, Option<(usize, Input)>)> = vec![(start, None)];
+    let mut seen = HashSet::from([start]);
+    let mut landed = HashSet::new();
+    let mut placements: Vec<Placement> = Vec::new();
+    let mut next = 0;
+
+    while let Some(&(state, _)) = states.get(next) {
+        place(&mut probe, state);
+
+        // A hard drop ends a path wherever it's given.
+        let distance = drop_distance(&probe);
+        let landing = State {
+            spot: Spot {
+                y: state.spot.y - distance,
+                ..state.spot
+            },
+            rotated: state.rotated && distance == 0,
+        };
+
+        if landed.insert(landing) {
+            let mut locked = probe.clone();
+            let mut events = Vec::new();
+            drop_piece(&mut locked, &mut events);
+
+            let mut spin = false;
+            let mut lines = 0;
+
+            for event in events.iter() {
+                match event {
+                    GameEvent::Locked { spin: true, .. } => spin = true,
+                    GameEvent::Cleared { rows } => lines = rows.len(),
+                    _ => {}
+                }
+            }
+
+            let duplicate = placements
+                .iter()
+                .any(|placement| placement.spin == spin && placement.game.world == locked.world);
+
+            if !duplicate {
+                placements.push(Placement {
+                    spot: landing.spot,
+                    spin,
+                    path: path(&states, next),
+                    game: locked,
+                    lines,
+                });
+            }
+        }
+
+        for input in [Input::Left, Input::Right, Input::Rotate, Input::SoftDrop] {
+            place(&mut probe, state);
+            step(&mut probe, input);
+
+            let reached = State {
+                spot: Spot::of(&probe),
+                rotated: probe.rotated,
+            };
+
+            if seen.insert(reached) {
+                states.push((reached, Some((next, input))));
+            }
+        }
+
+        next += 1;
+    }
+
+    placements
+}
+
+/// Moves the active piece like an input would, except that a soft drop never
+/// locks it and a hard drop does nothing.
+fn step(game: &mut Game, input: Input) {
+    match input {
+        Input::Left => move_piece(game, -1),
+        Input::Right => move_piece(game, 1),
+        Input::Rotate => rotate_piece(game),
+
+        Input::SoftDrop => {
+            if !detect_collision(game, game.y, &game.effective_piece()) {
+                game.y -= 1;
+                game.rotated = false;
+            }
+        }
+
+        Input::HardDrop | Input::Undo => {}
+    }
+}
+
+/// Where the active piece is when each of the inputs is given, if they are
+/// given one per tick.  The path ends at the input which locks the piece,
+/// and a hard drop is added if none does.
+pub fn follow(game: &Game, inputs: &[Input]) -> Vec<(Spot, Input)> {
+    let mut probe = Game {
+        history: Vec::new(),
+        ..game.clone()
+    };
+
+    let mut path = Vec::new();
+
+    for input in inputs.iter().copied().chain([Input::HardDrop]) {
+        path.push((Spot::of(&probe), input));
+
+        let resting = detect_collision(&probe, probe.y, &probe.effective_piece());
+        if input == Input::HardDrop || (input == Input::SoftDrop && resting) {
+            break;
+        }
+
+        step(&mut probe, input);
+    }
+
+    path
+}
+
+/// Inputs leading to a searched state, followed by a hard drop.
+fn path(states: &[(State, Option<(usize, Input)>)], index: usize) -> Vec<(Spot, Input)> {
+    let mut path = vec![(states[index].0.spot, Input::HardDrop)];
+    let mut from = states[index].1;
+
+    while let Some((parent, input)) = from {
+        path.push((states[parent].0.spot, input));
+        from = states[parent].1;
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::garbage_row;
+    use crate::game::test_game;
+    use crate::play::Play;
+    use crate::play::Randomizer;
+
+    /// A game of the given rows, listed from the top down, with the named
+    /// piece at its spawn.
+    fn spawned(rows: &[&str], name: &str) -> Game {
+        let world = rows.iter().rev().map(|row| garbage_row(row)).collect();
+        let mut game = test_game(10, world);
+        game.piece_index = game.pieces.names.iter().position(|n| n == name).unwrap();
+        game.x = game.spawn_x();
+        game.y = game.spawn_y();
+        game
+    }
+
+    const SLOT: [&str; 2] = ["..########", "...#######"];
+    const OVERHANG: [&str; 2] = ["######....", "#........."];
+
+    #[test]
+    fn same_boards_merge() {
+        // All four turns of an O leave the same boards, one per column.
+        let found = placements(&spawned(&[], "O"));
+        assert_eq!(found.len(), 9);
+
+        // An I lies flat or stands up either way round.
+        let found = placements(&spawned(&[], "I"));
+        assert_eq!(found.len(), 7 + 10);
+    }
+
+    #[test]
+    fn tucks() {
+        let found = placements(&spawned(&OVERHANG, "I"));
+
+        // Flat under the overhang, which a hard drop from above can't reach.
+        let tuck = found
+            .iter()
+            .find(|placement| placement.spot.y == 0 && placement.spot.x < 6)
+            .unwrap();
+        assert_eq!(tuck.spot.orient % 2, 0);
+        assert!(tuck.path.iter().any(|(_, input)| *input == Input::SoftDrop));
+    }
+
+    #[test]
+    fn t_slot_spins() {
+        // The T can only get under the overhang standing on its side, and
+        // then turn flat into the slot.
+        let found = placements(&spawned(&SLOT, "T"));
+        let spot = Spot {
+            x: 0,
+            y: 0,
+            orient: 2,
+        };
+
+        let spin = found
+            .iter()
+            .find(|placement| placement.spot == spot)
+            .unwrap();
+        assert!(spin.spin);
+        assert_eq!(spin.lines, 1);
+        assert!(spin.path.iter().rev().nth(1).unwrap().1 == Input::Rotate);
+
+        // Nothing else fits closely enough.
+        assert_eq!(found.iter().filter(|placement| placement.spin).count(), 1);
+    }
+
+    #[test]
+    fn paths_lock_at_their_spots() {
+        for rows in [&[][..], &OVERHANG[..], &SLOT[..]] {
+            for name in ["I", "J", "L", "O", "S", "T", "Z"] {
+                let game = spawned(rows, name);
+                let piece_count = game.pieces.len();
+
+                for placement in placements(&game) {
+                    let mut play = Play::new(game.clone(), Randomizer::new(1, piece_count), 0);
+                    let mut events = Vec::new();
+
+                    for (spot, input) in placement.path.iter() {
+                        assert!(Spot::of(&play.game) == *spot);
+                        play.tick(&[*input], &mut events);
+                    }
+
+                    let locked = events.iter().find_map(|event| match event {
+                        GameEvent::Locked {
+                            x, y, orient, spin, ..
+                        } => Some((
+                            Spot {
+                                x: *x,
+                                y: *y,
+                                orient: *orient,
+                            },
+                            *spin,
+                        )),
+                        _ => None,
+                    });
+
+                    assert!(locked == Some((placement.spot, placement.spin)));
+                    assert_eq!(play.stats.pieces, 1);
+                }
+            }
+        }
+    }
+}
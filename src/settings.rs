@@ -0,0 +1,519 @@
+use std::env;
+use std::process::exit;
+
+use crate::bot::Weights;
+use crate::fumen::Fumen;
+use crate::game::MAX_WIDTH;
+use crate::game::MIN_WIDTH;
+use crate::garbage::Garbage;
+use crate::garbage::TOWER_ROWS;
+use crate::play::Mode;
+use crate::protocol::Link;
+use crate::render::AgeScheme;
+use crate::render::ColorMode;
+use crate::world::Checkerboard;
+use crate::world::Empty;
+use crate::world::Import;
+use crate::world::Staircase;
+use crate::world::WorldGenerator;
+
+/// Undo depth in practice mode.
+const DEFAULT_UNDO_DEPTH: usize = 16;
+/// Replays store the depth in a byte.
+const MAX_UNDO_DEPTH: usize = 255;
+/// Replays store sprint, ultra and dig targets in two bytes.
+const MAX_TARGET: u32 = u16::MAX as u32;
+/// Garbage rows to dig through by default.
+const DEFAULT_DIG_ROWS: usize = 100;
+/// Pieces a headless bot places in a game which doesn't end by itself.
+const DEFAULT_AI_PIECES: u32 = 1000;
+
+pub struct Settings {
+    pub mode: Mode,
+
+    /// Lines to clear in a sprint.
+    pub lines: u32,
+
+    /// Seconds to play in an ultra.
+    pub time: u32,
+
+    /// Generator name, path to a world file or fumen for a new world, if not
+    /// the mode's default.
+    pub world: Option<String>,
+
+    /// Page of a fumen world to start from, counting from 1.
+    pub fumen_page: usize,
+
+    /// Garbage rows to start with, if not the mode's default.
+    pub garbage: Option<usize>,
+
+    /// Holes in each cheese row.
+    pub holes: usize,
+
+    /// Percentage of the non-hole garbage cells which are filled, if not the
+    /// generator's default.
+    pub density: Option<u32>,
+
+    /// Percentage of cheese rows whose holes don't line up with the row
+    /// below.
+    pub messiness: u32,
+
+    /// Garbage rows to clear in a dig, if not all of them.
+    pub depth: Option<usize>,
+
+    /// Built-in puzzle pack name or path to a puzzle pack file.
+    pub puzzle: Option<String>,
+
+    /// Board width for a new tower.  An existing tower keeps its own.
+    pub width: Option<usize>,
+
+    pub fullscreen: bool,
+
+    pub color_mode: ColorMode,
+
+    pub age_scheme: AgeScheme,
+
+    /// Hide the age colour legend.
+    pub no_legend: bool,
+
+    /// Built-in piece set name or path to a piece set file.
+    pub pieces: String,
+
+    /// Built-in theme name or path to a theme file.
+    pub theme: String,
+
+    /// How many placements can be undone.  Zero disables undo.
+    pub undo_depth: usize,
+
+    /// Play back how the tower was built instead of playing.
+    pub timelapse: bool,
+
+    /// Seed for the piece sequence, random if not given.
+    pub seed: Option<u64>,
+
+    /// File to record the game's inputs to.
+    pub record: Option<String>,
+
+    /// Replay file to play back instead of playing.
+    pub replay: Option<String>,
+
+    /// Play the replay back without a window and check where it ends up.
+    pub headless: bool,
+
+    /// Skip line clear, lock and drop animations, and snap the camera
+    /// instead of scrolling it.
+    pub reduced_motion: bool,
+
+    /// Let a bot play instead of the player.
+    pub ai: bool,
+
+    /// Bot to play with instead of the built-in one.
+    pub bot: Option<Link>,
+
+    /// Serve the built-in bot to other games instead of playing.
+    pub serve: Option<Link>,
+
+    /// How the bot scores boards.
+    pub ai_weights: Weights,
+
+    /// Have the bot take the next piece into account.
+    pub ai_lookahead: bool,
+
+    /// Pieces after which a headless bot stops, if the game goes on.
+    pub ai_pieces: u32,
+}
+
+impl Settings {
+    pub fn from_args() -> Settings {
+        let mut settings = Settings {
+            mode: Mode::Infinite,
+            lines: 40,
+            time: 120,
+            world: None,
+            fumen_page: 1,
+            garbage: None,
+            holes: 1,
+            density: None,
+            messiness: 100,
+            depth: None,
+            puzzle: None,
+            width: None,
+            fullscreen: false,
+            color_mode: ColorMode::Age,
+            age_scheme: AgeScheme::Linear,
+            no_legend: false,
+            pieces: "tetrominoes".to_string(),
+            theme: "default".to_string(),
+            undo_depth: 0,
+            timelapse: false,
+            seed: None,
+            record: None,
+            replay: None,
+            headless: false,
+            reduced_motion: false,
+            ai: false,
+            bot: None,
+            serve: None,
+            ai_weights: Weights::default(),
+            ai_lookahead: false,
+            ai_pieces: DEFAULT_AI_PIECES,
+        };
+
+        let mut args = env::args().skip(1);
+        let mut mode = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fullscreen" => settings.fullscreen = true,
+                "--no-legend" => settings.no_legend = true,
+                "--reduced-motion" => settings.reduced_motion = true,
+                "--timelapse" => settings.timelapse = true,
+                "--headless" => settings.headless = true,
+                "--practice" => settings.undo_depth = DEFAULT_UNDO_DEPTH,
+                "--ai" => settings.ai = true,
+                "--ai-lookahead" => settings.ai_lookahead = true,
+                "--serve-bot" => settings.serve = Some(Link::Stdio),
+
+                "--serve-bot-socket" => match args.next() {
+                    Some(path) => settings.serve = Some(Link::Socket(path)),
+
+                    None => {
+                        eprintln!("infinitris: --serve-bot-socket needs a socket path");
+                        exit(2);
+                    }
+                },
+
+                "--bot-command" => match args.next() {
+                    Some(command) => {
+                        settings.ai = true;
+                        settings.bot = Some(Link::Command(command));
+                    }
+
+                    None => {
+                        eprintln!("infinitris: --bot-command needs a command");
+                        exit(2);
+                    }
+                },
+
+                "--bot-socket" => match args.next() {
+                    Some(path) => {
+                        settings.ai = true;
+                        settings.bot = Some(Link::Socket(path));
+                    }
+
+                    None => {
+                        eprintln!("infinitris: --bot-socket needs a socket path");
+                        exit(2);
+                    }
+                },
+
+                "--ai-weights" => match args.next().as_deref().and_then(Weights::parse) {
+                    Some(weights) => settings.ai_weights = weights,
+
+                    None => {
+                        eprintln!(
+                            "infinitris: --ai-weights needs name=value pairs for height, lines, holes or bumpiness"
+                        );
+                        exit(2);
+                    }
+                },
+
+                "--ai-pieces" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(pieces) if pieces > 0 => settings.ai_pieces = pieces,
+
+                    _ => {
+                        eprintln!("infinitris: --ai-pieces needs a positive number");
+                        exit(2);
+                    }
+                },
+
+                "--undo" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(depth) if depth <= MAX_UNDO_DEPTH => settings.undo_depth = depth,
+
+                    _ => {
+                        eprintln!("infinitris: --undo needs a number up to {}", MAX_UNDO_DEPTH);
+                        exit(2);
+                    }
+                },
+
+                "--record" => match args.next() {
+                    Some(path) => settings.record = Some(path),
+
+                    None => {
+                        eprintln!("infinitris: --record needs a file");
+                        exit(2);
+                    }
+                },
+
+                "--replay" => match args.next() {
+                    Some(path) => settings.replay = Some(path),
+
+                    None => {
+                        eprintln!("infinitris: --replay needs a file");
+                        exit(2);
+                    }
+                },
+
+                "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => settings.seed = Some(seed),
+
+                    None => {
+                        eprintln!("infinitris: --seed needs a number");
+                        exit(2);
+                    }
+                },
+
+                "--age" => match args.next().as_deref().and_then(AgeScheme::parse) {
+                    Some(scheme) => settings.age_scheme = scheme,
+
+                    None => {
+                        eprintln!("infinitris: --age needs linear, log, time or session");
+                        exit(2);
+                    }
+                },
+
+                "--colors" => match args.next().as_deref().and_then(ColorMode::parse) {
+                    Some(mode) => settings.color_mode = mode,
+
+                    None => {
+                        eprintln!("infinitris: --colors needs age, classic or hybrid");
+                        exit(2);
+                    }
+                },
+
+                "--mode" => match args.next().as_deref().and_then(Mode::parse) {
+                    Some(parsed) => mode = Some(parsed),
+
+                    None => {
+                        eprintln!(
+                            "infinitris: --mode needs infinite, classic, sprint, ultra, dig or puzzle"
+                        );
+                        exit(2);
+                    }
+                },
+
+                "--lines" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(lines) if (1..=MAX_TARGET).contains(&lines) => settings.lines = lines,
+
+                    _ => {
+                        eprintln!("infinitris: --lines needs a number up to {}", MAX_TARGET);
+                        exit(2);
+                    }
+                },
+
+                "--time" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(time) if (1..=MAX_TARGET).contains(&time) => settings.time = time,
+
+                    _ => {
+                        eprintln!("infinitris: --time needs seconds up to {}", MAX_TARGET);
+                        exit(2);
+                    }
+                },
+
+                "--garbage" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(rows) if rows <= MAX_TARGET as usize => settings.garbage = Some(rows),
+
+                    _ => {
+                        eprintln!("infinitris: --garbage needs a number up to {}", MAX_TARGET);
+                        exit(2);
+                    }
+                },
+
+                "--holes" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(holes) if holes > 0 => settings.holes = holes,
+
+                    _ => {
+                        eprintln!("infinitris: --holes needs a positive number");
+                        exit(2);
+                    }
+                },
+
+                "--density" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(density) if (1..=100).contains(&density) => {
+                        settings.density = Some(density);
+                    }
+
+                    _ => {
+                        eprintln!("infinitris: --density needs a percentage above 0");
+                        exit(2);
+                    }
+                },
+
+                "--messiness" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(messiness) if messiness <= 100 => settings.messiness = messiness,
+
+                    _ => {
+                        eprintln!("infinitris: --messiness needs a percentage");
+                        exit(2);
+                    }
+                },
+
+                "--world" => match args.next() {
+                    Some(world) => settings.world = Some(world),
+
+                    None => {
+                        eprintln!("infinitris: --world needs a generator name, file or fumen");
+                        exit(2);
+                    }
+                },
+
+                "--fumen-page" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(page) if page > 0 => settings.fumen_page = page,
+
+                    _ => {
+                        eprintln!("infinitris: --fumen-page needs a positive number");
+                        exit(2);
+                    }
+                },
+
+                "--depth" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(depth) if depth > 0 => settings.depth = Some(depth),
+
+                    _ => {
+                        eprintln!("infinitris: --depth needs a positive number");
+                        exit(2);
+                    }
+                },
+
+                "--puzzle" => match args.next() {
+                    Some(pack) => settings.puzzle = Some(pack),
+
+                    None => {
+                        eprintln!("infinitris: --puzzle needs a pack name or file");
+                        exit(2);
+                    }
+                },
+
+                "--pieces" => match args.next() {
+                    Some(pieces) => settings.pieces = pieces,
+
+                    None => {
+                        eprintln!("infinitris: --pieces needs a set name or file");
+                        exit(2);
+                    }
+                },
+
+                "--theme" => match args.next() {
+                    Some(theme) => settings.theme = theme,
+
+                    None => {
+                        eprintln!("infinitris: --theme needs a theme name or file");
+                        exit(2);
+                    }
+                },
+
+                "--width" => {
+                    let width = args.next().and_then(|value| value.parse().ok());
+
+                    match width {
+                        Some(width) if (MIN_WIDTH..=MAX_WIDTH).contains(&width) => {
+                            settings.width = Some(width);
+                        }
+
+                        _ => {
+                            eprintln!(
+                                "infinitris: --width needs a number between {} and {}",
+                                MIN_WIDTH, MAX_WIDTH
+                            );
+                            exit(2);
+                        }
+                    }
+                }
+
+                _ => {
+                    eprintln!("infinitris: unknown option: {}", arg);
+                    exit(2);
+                }
+            }
+        }
+
+        // A puzzle pack always plays as puzzles, whatever order the options
+        // came in.
+        settings.mode = match (mode, &settings.puzzle) {
+            (Some(mode), Some(_)) if mode != Mode::Puzzle => {
+                eprintln!("infinitris: --puzzle can't be combined with another --mode");
+                exit(2);
+            }
+
+            (_, Some(_)) => Mode::Puzzle,
+            (mode, None) => mode.unwrap_or(settings.mode),
+        };
+
+        if settings.timelapse && settings.mode != Mode::Infinite {
+            eprintln!("infinitris: --timelapse is for the infinite tower");
+            exit(2);
+        }
+
+        if settings.mode == Mode::Puzzle && settings.puzzle.is_none() {
+            eprintln!("infinitris: --mode puzzle needs --puzzle");
+            exit(2);
+        }
+
+        // A replay has no place for the pack or the piece queue.
+        if settings.mode == Mode::Puzzle && settings.record.is_some() {
+            eprintln!("infinitris: puzzles can't be recorded");
+            exit(2);
+        }
+
+        if settings.ai && settings.replay.is_some() {
+            eprintln!("infinitris: --ai can't take over a replay");
+            exit(2);
+        }
+
+        if settings.headless && settings.replay.is_none() && !settings.ai {
+            eprintln!("infinitris: --headless needs --replay or --ai");
+            exit(2);
+        }
+
+        settings
+    }
+
+    /// Generator for a new world in the chosen mode.
+    pub fn world(&self) -> Box<dyn WorldGenerator> {
+        let (name, rows) = match self.mode {
+            Mode::Infinite => ("random", TOWER_ROWS),
+            Mode::Dig => ("cheese", DEFAULT_DIG_ROWS),
+            _ => ("cheese", 0),
+        };
+
+        let name = self.world.as_deref().unwrap_or(name);
+        let rows = self.garbage.unwrap_or(rows);
+
+        match name {
+            "empty" => Box::new(Empty),
+
+            "random" => Box::new(Garbage {
+                rows,
+                holes: 0,
+                density: self.density.unwrap_or(50),
+                messiness: 100,
+            }),
+
+            "cheese" => Box::new(Garbage {
+                rows,
+                holes: self.holes,
+                density: self.density.unwrap_or(100),
+                messiness: self.messiness,
+            }),
+
+            "single-well" => Box::new(Garbage {
+                rows,
+                holes: self.holes,
+                density: self.density.unwrap_or(100),
+                messiness: 0,
+            }),
+
+            "checkerboard" => Box::new(Checkerboard { rows }),
+            "staircase" => Box::new(Staircase { rows }),
+
+            data if data.contains("v115@") => Box::new(Fumen {
+                data: data.to_string(),
+                page: self.fumen_page,
+            }),
+
+            path => Box::new(Import {
+                path: path.to_string(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::fs::rename;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::Generation;
+use crate::game::DEFAULT_WIDTH;
+use crate::game::MAX_WIDTH;
+use crate::game::MIN_WIDTH;
+use crate::game::UNKNOWN_PIECE;
+use crate::garbage::TOWER_ROWS;
+
+const STATE_PATH: &str = "infinitris.state";
+const STATE_TMP_PATH: &str = ".infinitris.state.tmp";
+
+pub fn save_game(game: &Game) {
+    let mut file = File::create(STATE_TMP_PATH).unwrap();
+    let [a, b, c, d] = (game.layer as u32).to_be_bytes();
+    file.write_all(&[7, game.width as u8, a, b, c, d, 0, 0])
+        .unwrap();
+    file.write_all(&[game.pieces.name.len() as u8]).unwrap();
+    file.write_all(game.pieces.name.as_bytes()).unwrap();
+    file.write_all(&(game.history.len() as u32).to_be_bytes())
+        .unwrap();
+    for generation in game.history.iter() {
+        file.write_all(&generation.time.to_be_bytes()).unwrap();
+        file.write_all(&generation.session.to_be_bytes()).unwrap();
+        file.write_all(&[generation.piece]).unwrap();
+    }
+    for row in game.world.iter() {
+        for cell in row.iter() {
+            file.write_all(&[
+                ((cell.gen >> 24) & 0xff) as u8,
+                ((cell.gen >> 16) & 0xff) as u8,
+                ((cell.gen >> 8) & 0xff) as u8,
+                (cell.gen & 0xff) as u8,
+                cell.piece,
+            ])
+            .unwrap();
+        }
+    }
+    file.flush().unwrap();
+    rename(STATE_TMP_PATH, STATE_PATH).unwrap();
+}
+
+/// Returns false if there is no saved state, or an error if the tower was
+/// built with another piece set than the game's.
+pub fn load_game(game: &mut Game) -> Result<bool, String> {
+    let mut file = match File::open(STATE_PATH) {
+        Ok(file) => file,
+        Err(_) => return Ok(false),
+    };
+
+    let mut header = [0u8; 8];
+
+    let size = file.read(&mut header).unwrap();
+    if size < header.len() {
+        panic!("Invalid state (no header)");
+    }
+
+    let version = header[0];
+    match version {
+        1..=7 => {
+            game.width = match version {
+                1 => DEFAULT_WIDTH,
+                _ => header[1] as usize,
+            };
+
+            if game.width < MIN_WIDTH || game.width > MAX_WIDTH {
+                panic!("Invalid state (width {})", game.width);
+            }
+
+            // The size of the initial garbage is stored since version 6.
+            game.layer = match version {
+                6 | 7 => u32::from_be_bytes(header[2..6].try_into().unwrap()) as usize,
+                _ => TOWER_ROWS,
+            };
+
+            // The piece set is stored since version 7.  Piece types in
+            // earlier versions are taken to be of the set in use.
+            if version >= 7 {
+                let mut len = [0u8; 1];
+                file.read_exact(&mut len)
+                    .expect("Invalid state (no piece set)");
+                let mut name = vec![0u8; len[0] as usize];
+                file.read_exact(&mut name)
+                    .expect("Invalid state (truncated piece set)");
+                let name = String::from_utf8_lossy(&name);
+
+                if name != game.pieces.name {
+                    return Err(format!(
+                        "the tower was built with {}, so it needs --pieces {}",
+                        name, name
+                    ));
+                }
+            }
+
+            // Piece types are stored since version 3.
+            let cell_size = match version {
+                1 | 2 => 4,
+                _ => 5,
+            };
+
+            // Generation history is stored since version 4, and piece types
+            // in it since version 5.
+            let record_size = match version {
+                4 => 12,
+                _ => 13,
+            };
+
+            game.history.clear();
+            if version >= 4 {
+                let mut count = [0u8; 4];
+                file.read_exact(&mut count)
+                    .expect("Invalid state (no history)");
+
+                for _ in 0..u32::from_be_bytes(count) {
+                    let mut record = [0u8; 13];
+                    file.read_exact(&mut record[..record_size])
+                        .expect("Invalid state (truncated history)");
+
+                    game.history.push(Generation {
+                        time: u64::from_be_bytes(record[0..8].try_into().unwrap()),
+                        session: u32::from_be_bytes(record[8..12].try_into().unwrap()),
+                        piece: match record_size {
+                            12 => UNKNOWN_PIECE,
+                            _ => record[12],
+                        },
+                    });
+                }
+            }
+
+            let mut max_gen: u32 = 0;
+
+            loop {
+                let mut bytes = vec![0u8; game.width * cell_size];
+
+                let size = file.read(&mut bytes).unwrap();
+                if size == 0 {
+                    break;
+                }
+
+                if size != game.width * cell_size {
+                    panic!("Invalid state (read length {})", size);
+                }
+
+                let mut row = vec![Cell::default(); game.width];
+
+                for (i, cell) in row.iter_mut().enumerate() {
+                    let bytes = &bytes[i * cell_size..];
+
+                    cell.gen = ((bytes[0] as u32) << 24)
+                        | ((bytes[1] as u32) << 16)
+                        | ((bytes[2] as u32) << 8)
+                        | (bytes[3] as u32);
+                    cell.piece = match cell_size {
+                        4 => UNKNOWN_PIECE,
+                        _ => bytes[4],
+                    };
+                    max_gen = u32::max(max_gen, cell.gen);
+                }
+
+                game.world.push(row);
+            }
+
+            // Generations which were cleared completely are only left in the
+            // history.
+            game.next_gen = u32::max(max_gen + 1, game.history.len() as u32);
+            game.history
+                .resize(game.next_gen as usize, Generation::default());
+        }
+
+        _ => panic!("Invalid state (version {})", version),
+    }
+
+    Ok(true)
+}
+
+const JOURNAL_PATH: &str = "infinitris.journal";
+const JOURNAL_ENTRY_SIZE: usize = 20;
+
+/// A locked piece, as recorded in the journal.
+#[derive(Clone, Copy)]
+pub struct JournalEntry {
+    pub time: u64,
+    pub session: u32,
+    pub piece_index: u8,
+    pub orient: u8,
+    pub x: u16,
+    pub y: u32,
+}
+
+/// Every lock since the journal was started, on top of a snapshot of the
+/// world at that point.
+pub struct Journal {
+    pub width: usize,
+    /// Name of the piece set the entries' piece types belong to.
+    pub pieces: String,
+    pub next_gen: u32,
+    pub world: Vec<Vec<Cell>>,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// True if replaying the journal ends up at the game's generation.
+    pub fn matches(&self, game: &Game) -> bool {
+        self.width == game.width
+            && self.pieces == game.pieces.name
+            && self.next_gen as usize + self.entries.len() == game.next_gen as usize
+    }
+}
+
+/// Starts the journal over from the current world.
+pub fn start_journal(game: &Game) {
+    let mut file = BufWriter::new(File::create(JOURNAL_PATH).unwrap());
+    file.write_all(&[2, game.width as u8, 0, 0, 0, 0, 0, 0])
+        .unwrap();
+    file.write_all(&game.next_gen.to_be_bytes()).unwrap();
+    file.write_all(&(game.world.len() as u32).to_be_bytes())
+        .unwrap();
+    file.write_all(&[game.pieces.name.len() as u8]).unwrap();
+    file.write_all(game.pieces.name.as_bytes()).unwrap();
+    for row in game.world.iter() {
+        for cell in row.iter() {
+            file.write_all(&cell.gen.to_be_bytes()).unwrap();
+            file.write_all(&[cell.piece]).unwrap();
+        }
+    }
+    file.flush().unwrap();
+}
+
+pub fn append_journal(entry: &JournalEntry) {
+    let mut file = OpenOptions::new().append(true).open(JOURNAL_PATH).unwrap();
+
+    let mut bytes = Vec::with_capacity(JOURNAL_ENTRY_SIZE);
+    bytes.extend_from_slice(&entry.time.to_be_bytes());
+    bytes.extend_from_slice(&entry.session.to_be_bytes());
+    bytes.extend_from_slice(&[entry.piece_index, entry.orient]);
+    bytes.extend_from_slice(&entry.x.to_be_bytes());
+    bytes.extend_from_slice(&entry.y.to_be_bytes());
+
+    file.write_all(&bytes).unwrap();
+}
+
+/// Removes the last entry after its lock has been undone.
+pub fn unwind_journal(game: &Game) {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(JOURNAL_PATH)
+        .unwrap();
+    let mut header = [0u8; 17];
+    (&file).read_exact(&mut header).unwrap();
+
+    let rows = u32::from_be_bytes(header[12..16].try_into().unwrap()) as u64;
+    let snapshot = header.len() as u64 + header[16] as u64 + rows * header[1] as u64 * 5;
+    let len = file.metadata().unwrap().len();
+
+    if len >= snapshot + JOURNAL_ENTRY_SIZE as u64 {
+        file.set_len(len - JOURNAL_ENTRY_SIZE as u64).unwrap();
+    } else {
+        // The lock came before the journal was started.
+        start_journal(game);
+    }
+}
+
+/// Returns None if there is no usable journal.  The journal is only a
+/// record, so a damaged one is started over rather than treated as fatal.
+pub fn load_journal() -> Option<Journal> {
+    let mut file = BufReader::new(File::open(JOURNAL_PATH).ok()?);
+
+    let mut header = [0u8; 17];
+    file.read_exact(&mut header).ok()?;
+
+    // Journals from before the piece set was stored are started over.
+    if header[0] != 2 {
+        return None;
+    }
+
+    let mut name = vec![0u8; header[16] as usize];
+    file.read_exact(&mut name).ok()?;
+
+    let word = |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+
+    let mut journal = Journal {
+        width: header[1] as usize,
+        pieces: String::from_utf8(name).ok()?,
+        next_gen: word(8),
+        world: Vec::new(),
+        entries: Vec::new(),
+    };
+
+    // Generation 0 stands for empty cells, so no world starts before 1.
+    if journal.width < MIN_WIDTH || journal.width > MAX_WIDTH || journal.next_gen == 0 {
+        return None;
+    }
+
+    for _ in 0..word(12) {
+        let mut bytes = vec![0u8; journal.width * 5];
+        file.read_exact(&mut bytes).ok()?;
+
+        let row = bytes
+            .chunks(5)
+            .map(|cell| Cell {
+                gen: u32::from_be_bytes(cell[0..4].try_into().unwrap()),
+                piece: cell[4],
+            })
+            .collect();
+
+        journal.world.push(row);
+    }
+
+    // A partially written entry at the end is ignored.
+    let mut bytes = [0u8; JOURNAL_ENTRY_SIZE];
+    while file.read_exact(&mut bytes).is_ok() {
+        journal.entries.push(JournalEntry {
+            time: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            session: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+            piece_index: bytes[12],
+            orient: bytes[13],
+            x: u16::from_be_bytes(bytes[14..16].try_into().unwrap()),
+            y: u32::from_be_bytes(bytes[16..20].try_into().unwrap()),
+        });
+    }
+
+    Some(journal)
+}
@@ -0,0 +1,113 @@
+use std::fs;
+
+use sdl2::pixels::Color;
+use serde::Deserialize;
+
+static BUILTIN_THEMES: [(&str, &str); 4] = [
+    ("default", include_str!("themes/default.toml")),
+    ("deuteranopia", include_str!("themes/deuteranopia.toml")),
+    ("protanopia", include_str!("themes/protanopia.toml")),
+    ("high-contrast", include_str!("themes/high-contrast.toml")),
+];
+
+/// How a single block is drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockStyle {
+    Flat,
+    Bevelled,
+    Outlined,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: Option<String>,
+    background: [u8; 3],
+    panel: [u8; 3],
+    shadow: [u8; 4],
+    garbage: [u8; 3],
+    block: BlockStyle,
+    pieces: Option<Vec<[u8; 3]>>,
+    age: Option<Vec<[u8; 3]>>,
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    /// Ground, gap and the area below the minimap.
+    pub panel: Color,
+    pub shadow: Color,
+    /// Cells which weren't placed as a known piece.
+    pub garbage: Color,
+    pub block: BlockStyle,
+    /// Replaces the piece set's colours, repeating in other shades if there
+    /// are more pieces.
+    pub pieces: Vec<Color>,
+    /// Age gradient stops from oldest to newest.  The classic sine-based
+    /// gradient is used if there are none.
+    pub age: Vec<Color>,
+}
+
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::RGB(r, g, b)
+}
+
+impl Theme {
+    /// Loads a built-in theme by name, or a theme from a TOML file.
+    pub fn load(name: &str) -> Result<Theme, String> {
+        match BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name) {
+            Some((builtin, text)) => Theme::parse(text, builtin),
+
+            None => {
+                let text = fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+                Theme::parse(&text, name).map_err(|e| format!("{}: {}", name, e))
+            }
+        }
+    }
+
+    /// All built-in themes, followed by `extra` unless it is one of them.
+    pub fn load_all(extra: &Theme) -> Vec<Theme> {
+        let mut themes: Vec<Theme> = BUILTIN_THEMES
+            .iter()
+            .map(|(builtin, text)| Theme::parse(text, builtin).unwrap())
+            .collect();
+
+        if themes.iter().all(|theme| theme.name != extra.name) {
+            themes.push(extra.clone());
+        }
+
+        themes
+    }
+
+    fn parse(text: &str, default_name: &str) -> Result<Theme, String> {
+        let file: ThemeFile = toml::from_str(text).map_err(|e| e.to_string())?;
+
+        if file.pieces.as_ref().is_some_and(|pieces| pieces.is_empty()) {
+            return Err("no piece colours".to_string());
+        }
+
+        if file.age.as_ref().is_some_and(|age| age.len() < 2) {
+            return Err("age gradient needs at least two colours".to_string());
+        }
+
+        let [r, g, b, a] = file.shadow;
+
+        Ok(Theme {
+            name: file.name.unwrap_or_else(|| default_name.to_string()),
+            background: rgb(file.background),
+            panel: rgb(file.panel),
+            shadow: Color::RGBA(r, g, b, a),
+            garbage: rgb(file.garbage),
+            block: file.block,
+            pieces: file
+                .pieces
+                .unwrap_or_default()
+                .into_iter()
+                .map(rgb)
+                .collect(),
+            age: file.age.unwrap_or_default().into_iter().map(rgb).collect(),
+        })
+    }
+}
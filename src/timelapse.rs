@@ -0,0 +1,350 @@
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::Instant;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::sys::SDL_RendererFlags;
+use sdl2::EventPump;
+
+use crate::anim::Animations;
+use crate::camera::Camera;
+use crate::font::render_text;
+use crate::game::advance_game;
+use crate::game::Cell;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::layout::Layout;
+use crate::render::render_game;
+use crate::render::toggle_fullscreen;
+use crate::render::Canvas;
+use crate::render::Scene;
+use crate::render::Style;
+use crate::state::Journal;
+use crate::state::JournalEntry;
+
+const DEFAULT_SPEED: u32 = 8;
+const MAX_SPEED: u32 = 1024;
+/// Steps which may pile up while the window isn't being drawn, in seconds of
+/// playback.
+const MAX_BACKLOG: f64 = 0.25;
+
+enum Source {
+    /// Locks replayed through the engine.
+    Journal(Vec<JournalEntry>),
+
+    /// Without a journal, the final world is revealed one generation at a
+    /// time.  Cleared rows are lost, so this only shows what remains.
+    Generations(Vec<Vec<Cell>>),
+}
+
+struct Timelapse {
+    start: Game,
+    game: Game,
+    source: Source,
+    step: usize,
+    finished: bool,
+    /// Whether the game's active piece is the next one to be placed.
+    upcoming: bool,
+}
+
+/// Puts the journal entry's piece in place, unless it doesn't fit the world.
+fn place(game: &mut Game, entry: &JournalEntry) -> bool {
+    if entry.piece_index as usize >= game.pieces.len() {
+        return false;
+    }
+
+    let piece = game
+        .pieces
+        .effective_piece(entry.piece_index as usize, entry.orient % 4);
+
+    if entry.x as usize + piece.width > game.width || entry.y as usize > game.world.len() {
+        return false;
+    }
+
+    game.piece_index = entry.piece_index as usize;
+    game.orient = entry.orient % 4;
+    game.x = entry.x as usize;
+    game.y = entry.y as usize;
+    true
+}
+
+impl Timelapse {
+    fn new(game: Game, journal: Option<Journal>) -> Timelapse {
+        let (start, source) = match journal {
+            Some(journal) if journal.matches(&game) => {
+                let history = game.history[..journal.next_gen as usize].to_vec();
+                let last = history.last().copied().unwrap_or_default();
+
+                let start = Game {
+                    world: journal.world,
+                    next_gen: journal.next_gen,
+                    history,
+                    time: last.time,
+                    session: last.session.max(1),
+                    ..game
+                };
+
+                (start, Source::Journal(journal.entries))
+            }
+
+            _ => {
+                let start = Game {
+                    world: Vec::new(),
+                    next_gen: 1,
+                    ..game.clone()
+                };
+
+                (start, Source::Generations(game.world))
+            }
+        };
+
+        let mut timelapse = Timelapse {
+            game: start.clone(),
+            start,
+            source,
+            step: 0,
+            finished: false,
+            upcoming: false,
+        };
+
+        timelapse.restart();
+        timelapse
+    }
+
+    fn steps(&self) -> usize {
+        match &self.source {
+            Source::Journal(entries) => entries.len(),
+            Source::Generations(_) => self.start.history.len() - 1,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.game = self.start.clone();
+        self.step = 0;
+        self.finished = false;
+        self.sync();
+    }
+
+    /// Plays one step.  Returns false when there are no more, or if the
+    /// journal doesn't fit the world.
+    fn advance(&mut self, events: &mut Vec<GameEvent>) -> bool {
+        if self.finished || self.step >= self.steps() {
+            self.finished = true;
+            return false;
+        }
+
+        if let Source::Journal(entries) = &self.source {
+            let entry = entries[self.step];
+            let game = &mut self.game;
+
+            game.time = entry.time;
+            game.session = entry.session.max(1);
+
+            if !place(game, &entry) || !advance_game(game, events) {
+                self.finished = true;
+                return false;
+            }
+        }
+
+        self.step += 1;
+        true
+    }
+
+    /// Brings the displayed game up to the current step.
+    fn sync(&mut self) {
+        let game = &mut self.game;
+
+        match &self.source {
+            Source::Journal(entries) => {
+                // Show the next piece where it's going to land.
+                self.upcoming = !self.finished
+                    && entries
+                        .get(self.step)
+                        .is_some_and(|entry| place(game, entry));
+
+                if let Some(next) = entries.get(self.step + 1) {
+                    if (next.piece_index as usize) < game.pieces.len() {
+                        game.next_piece_index = next.piece_index as usize;
+                    }
+                }
+            }
+
+            Source::Generations(world) => {
+                let gen = self.step as u32;
+
+                // Rows are only ever removed as a whole, so a remaining row
+                // existed as soon as its oldest cell did.
+                game.world = world
+                    .iter()
+                    .filter(|row| row.iter().any(|cell| !cell.is_empty() && cell.gen <= gen))
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| {
+                                if cell.gen <= gen {
+                                    *cell
+                                } else {
+                                    Cell::default()
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                game.next_gen = gen + 1;
+
+                let generation = game.history[gen as usize];
+                game.time = generation.time;
+                // Generations from before sessions were recorded have none.
+                game.session = generation.session.max(1);
+            }
+        }
+    }
+}
+
+/// Replays how the tower was built until the window is closed.
+pub fn run_timelapse(
+    canvas: &mut Canvas,
+    event_pump: &mut EventPump,
+    style: &Style,
+    reduced_motion: bool,
+    game: Game,
+    journal: Option<Journal>,
+) {
+    let vsync = canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32 != 0;
+    let frame = Duration::from_secs(1) / 60;
+    let reconstructed = !journal
+        .as_ref()
+        .is_some_and(|journal| journal.matches(&game));
+
+    let mut timelapse = Timelapse::new(game, journal);
+    let mut events = Vec::new();
+    let mut anims = Animations::default();
+    let mut camera = Camera::new(timelapse.game.world.len() as f64);
+    let mut speed = DEFAULT_SPEED;
+    let mut paused = false;
+    let mut due = 0.0;
+    let mut last_frame = Instant::now();
+
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return,
+
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => match keycode {
+                    Keycode::Space | Keycode::P => paused = !paused,
+                    Keycode::Plus | Keycode::Equals | Keycode::KpPlus => {
+                        speed = u32::min(speed * 2, MAX_SPEED);
+                    }
+                    Keycode::Minus | Keycode::KpMinus => speed = u32::max(speed / 2, 1),
+                    Keycode::Right if paused => {
+                        timelapse.advance(&mut events);
+                    }
+                    Keycode::Home => {
+                        timelapse.restart();
+                        anims = Animations::default();
+                        due = 0.0;
+                    }
+                    Keycode::F11 => toggle_fullscreen(canvas),
+                    _ => {}
+                },
+
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = now - last_frame;
+        last_frame = now;
+
+        if !paused {
+            due = f64::min(
+                due + elapsed.as_secs_f64() * speed as f64,
+                1.0 + speed as f64 * MAX_BACKLOG,
+            );
+
+            while due >= 1.0 {
+                due -= 1.0;
+
+                if !timelapse.advance(&mut events) {
+                    due = 0.0;
+                    break;
+                }
+            }
+        }
+
+        timelapse.sync();
+
+        for event in events.drain(..) {
+            if !reduced_motion {
+                anims.start(event, now);
+            }
+        }
+
+        anims.prune(now);
+
+        let surface = timelapse.game.world.len() as f64;
+        if reduced_motion {
+            camera.level = surface;
+        } else {
+            camera.update(surface, 1.0, elapsed);
+        }
+
+        let layout = Layout::for_canvas(canvas, timelapse.game.width);
+
+        let scene = Scene {
+            game: &timelapse.game,
+            anims: &anims,
+            camera: &camera,
+            fall: 0.0,
+            now,
+            piece: timelapse.upcoming,
+            next: true,
+            hint: None,
+        };
+
+        render_game(canvas, &layout, style, &scene);
+
+        let status = if paused {
+            " PAUSED"
+        } else if timelapse.finished {
+            " END"
+        } else {
+            ""
+        };
+
+        render_text(
+            canvas,
+            layout.left + layout.text_margin(),
+            layout.text_margin(),
+            layout.text_scale(),
+            Color::RGB(255, 255, 255),
+            &format!(
+                "{} {}/{} X{}{}",
+                if reconstructed {
+                    "REBUILD"
+                } else {
+                    "TIMELAPSE"
+                },
+                timelapse.step,
+                timelapse.steps(),
+                speed,
+                status
+            ),
+        );
+
+        canvas.present();
+
+        if !vsync {
+            sleep(frame);
+        }
+    }
+}
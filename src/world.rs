@@ -0,0 +1,133 @@
+use std::fs::read_to_string;
+
+use rand::RngCore;
+
+use crate::game::Cell;
+use crate::game::UNKNOWN_PIECE;
+use crate::garbage::GARBAGE_GEN;
+
+/// Builds the rows a new world starts with, from the bottom up.  Every row
+/// has at least one hole and at least one block.
+pub trait WorldGenerator {
+    fn generate(&self, rng: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String>;
+
+    /// Short account of the generator and its parameters, which tells apart
+    /// records set on different worlds.
+    fn describe(&self) -> String;
+}
+
+fn block(filled: bool) -> Cell {
+    if filled {
+        Cell {
+            gen: GARBAGE_GEN,
+            piece: UNKNOWN_PIECE,
+        }
+    } else {
+        Cell::default()
+    }
+}
+
+/// Nothing at all.
+pub struct Empty;
+
+impl WorldGenerator for Empty {
+    fn generate(&self, _: &mut dyn RngCore, _: usize) -> Result<Vec<Vec<Cell>>, String> {
+        Ok(Vec::new())
+    }
+
+    fn describe(&self) -> String {
+        "empty".to_string()
+    }
+}
+
+/// Alternating blocks and holes.
+pub struct Checkerboard {
+    pub rows: usize,
+}
+
+impl WorldGenerator for Checkerboard {
+    fn generate(&self, _: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+        Ok((0..self.rows)
+            .map(|y| (0..width).map(|x| block((x + y) % 2 == 0)).collect())
+            .collect())
+    }
+
+    fn describe(&self) -> String {
+        format!("checkerboard {}", self.rows)
+    }
+}
+
+/// Steps climbing from left to right, starting over when they reach the
+/// right edge.
+pub struct Staircase {
+    pub rows: usize,
+}
+
+impl WorldGenerator for Staircase {
+    fn generate(&self, _: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+        Ok((0..self.rows)
+            .map(|y| (0..width).map(|x| block(x > y % (width - 1))).collect())
+            .collect())
+    }
+
+    fn describe(&self) -> String {
+        format!("staircase {}", self.rows)
+    }
+}
+
+/// Rows drawn in a text file, top row first: '#' is a block and '.' is a
+/// hole.  Blank lines and lines starting with ';' are skipped.
+pub struct Import {
+    pub path: String,
+}
+
+impl WorldGenerator for Import {
+    fn generate(&self, _: &mut dyn RngCore, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+        let error = |e: String| format!("{}: {}", self.path, e);
+        let text = read_to_string(&self.path).map_err(|e| error(e.to_string()))?;
+        parse_rows(&text, width).map_err(error)
+    }
+
+    fn describe(&self) -> String {
+        format!("file {}", self.path)
+    }
+}
+
+/// Parses rows drawn like in a world file, returning them from the bottom up.
+pub fn parse_rows(text: &str, width: usize) -> Result<Vec<Vec<Cell>>, String> {
+    let mut world = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let row = line
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(block(true)),
+                '.' => Ok(block(false)),
+                _ => Err(format!("line {}: unexpected '{}'", n + 1, c)),
+            })
+            .collect::<Result<Vec<Cell>, String>>()?;
+
+        if row.len() != width {
+            return Err(format!(
+                "line {}: {} cells on a board {} cells wide",
+                n + 1,
+                row.len(),
+                width
+            ));
+        }
+
+        if row.iter().all(|cell| cell.is_empty()) || row.iter().all(|cell| !cell.is_empty()) {
+            return Err(format!("line {}: a row needs holes and blocks", n + 1));
+        }
+
+        world.push(row);
+    }
+
+    world.reverse();
+    Ok(world)
+}